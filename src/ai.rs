@@ -0,0 +1,198 @@
+use rand::seq::SliceRandom;
+use serde_derive::Deserialize;
+
+use crate::effect::EffectKind;
+use crate::game::Game;
+use crate::map::Level;
+use crate::monster::{fire, move_to, Attack};
+use crate::point::Point;
+
+/// The eight directions a creature can step in
+pub const DIRS: [Point; 8] = [
+    Point(-1, -1),
+    Point(0, -1),
+    Point(1, -1),
+    Point(-1, 0),
+    Point(1, 0),
+    Point(-1, 1),
+    Point(0, 1),
+    Point(1, 1),
+];
+
+/// How a monster decides what to do on its turn, set per monster with `behavior` in monsters.toml
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Behavior {
+    /// never moves, but will hit enemies that stand next to it
+    Still,
+    /// wanders around at random and ignores everyone
+    Wander,
+    /// wanders until it sees an enemy, then chases them down. Friendly ones follow the player
+    /// around instead of wandering.
+    Hunt,
+}
+impl Default for Behavior {
+    fn default() -> Behavior {
+        Behavior::Hunt
+    }
+}
+
+/// What a monster knows about its enemies
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Awareness {
+    Unaware,
+    /// caught a glimpse of an enemy or lost track of them, and will go look for a few turns
+    Suspicious(u32),
+    Hunting,
+}
+
+/// How many turns a monster keeps looking for an enemy before it forgets about them
+const SUSPICION_TURNS: u32 = 10;
+
+/// Allies stay about this close to the player when there's nothing to fight
+const FOLLOW_DISTANCE: i32 = 2;
+
+/// Updates what the monster at `idx` knows about its enemies based on what it can see, going
+/// after the closest one in sight.
+pub fn perceive(idx: usize, level: &mut Level) {
+    let mon = &level.monsters[idx];
+    let enemy = level
+        .monsters
+        .iter()
+        .enumerate()
+        .filter(|&(i, m)| {
+            i != idx && m.hp > 0 && mon.hostile(m) && level.can_see(mon.pos, m.pos, mon.fov)
+        })
+        .map(|(_, m)| m.pos)
+        .min_by_key(|&p| (p - mon.pos).len2());
+    let sees = enemy.is_some();
+    let close = enemy.map_or(false, |p| {
+        (p - mon.pos).len2() * 4 <= (mon.fov * mon.fov) as i32
+    });
+
+    let mon = &mut level.monsters[idx];
+    mon.awareness = match (mon.awareness, sees) {
+        (Awareness::Unaware, true) if !close => Awareness::Suspicious(SUSPICION_TURNS),
+        (_, true) => Awareness::Hunting,
+        (Awareness::Hunting, false) => Awareness::Suspicious(SUSPICION_TURNS),
+        (Awareness::Suspicious(0), false) => Awareness::Unaware,
+        (Awareness::Suspicious(n), false) => Awareness::Suspicious(n - 1),
+        (Awareness::Unaware, false) => Awareness::Unaware,
+    };
+    if sees {
+        mon.target = enemy;
+    } else if mon.awareness == Awareness::Unaware || mon.target == Some(mon.pos) {
+        mon.target = None;
+    }
+}
+
+/// Lets the monster at `idx` in the current level take its turn.
+pub fn act(idx: usize, game: &mut Game) {
+    let level = game.levels.cur_mut();
+    if level.monsters[idx].hp <= 0 || level.monsters[idx].effects.has(EffectKind::Stun) {
+        return;
+    }
+    perceive(idx, level);
+    let mon = &level.monsters[idx];
+    let pos = mon.pos;
+    let ppos = level.monsters[0].pos;
+    if let (Awareness::Hunting, Some(target)) = (mon.awareness, mon.target) {
+        if mon.behavior != Behavior::Wander {
+            if let Some(attack) = shot_at(level, idx, target) {
+                fire(
+                    idx,
+                    target,
+                    &attack,
+                    level,
+                    &game.info,
+                    &mut game.messages,
+                    &mut game.play_rng,
+                );
+                return;
+            }
+        }
+    }
+    let dpos = match (mon.behavior, mon.awareness, mon.target) {
+        (Behavior::Still, Awareness::Hunting, Some(target)) => {
+            let d = target - pos;
+            if d.0.abs() <= 1 && d.1.abs() <= 1 {
+                Some(d)
+            } else {
+                None
+            }
+        }
+        (Behavior::Still, _, _) => None,
+        (Behavior::Hunt, Awareness::Hunting, Some(target))
+        | (Behavior::Hunt, Awareness::Suspicious(_), Some(target)) => {
+            approach(level, idx, target).or_else(|| wander(level, pos, &mut game.play_rng))
+        }
+        // allies with nothing to fight tag along after the player
+        (Behavior::Hunt, _, _) if mon.friendly => {
+            if (ppos - pos).len2() > FOLLOW_DISTANCE * FOLLOW_DISTANCE {
+                approach(level, idx, ppos)
+            } else {
+                None
+            }
+        }
+        _ => wander(level, pos, &mut game.play_rng),
+    };
+    if let Some(dpos) = dpos {
+        move_to(
+            idx,
+            dpos,
+            level,
+            &game.info,
+            &mut game.messages,
+            &mut game.play_rng,
+        );
+    }
+}
+
+/// A ranged attack the monster at `idx` could hit `target` with, if it isn't already next to it
+fn shot_at(level: &Level, idx: usize, target: Point) -> Option<Attack> {
+    let mon = &level.monsters[idx];
+    let d = target - mon.pos;
+    if d.0.abs() <= 1 && d.1.abs() <= 1 {
+        return None;
+    }
+    mon.attacks
+        .iter()
+        .filter(|a| a.range.map_or(false, |r| d.len2() <= (r * r) as i32))
+        .find(|a| {
+            level
+                .projectile_path(mon.pos, target, a.range.unwrap())
+                .last()
+                == Some(&target)
+        })
+        .cloned()
+}
+
+fn wander<R: rand::Rng>(level: &Level, pos: Point, rng: &mut R) -> Option<Point> {
+    let free: Vec<Point> = DIRS
+        .iter()
+        .copied()
+        .filter(|&d| level.passable(pos + d) && level.monster_at(pos + d).is_none())
+        .collect();
+    free.choose(rng).copied()
+}
+
+// greedy step towards the target, sliding along walls if the diagonal is blocked
+fn approach(level: &Level, idx: usize, target: Point) -> Option<Point> {
+    let mon = &level.monsters[idx];
+    let pos = mon.pos;
+    let d = target - pos;
+    let (sx, sy) = (d.0.signum(), d.1.signum());
+    let steps = if d.0.abs() > d.1.abs() {
+        [Point(sx, sy), Point(sx, 0), Point(0, sy)]
+    } else {
+        [Point(sx, sy), Point(0, sy), Point(sx, 0)]
+    };
+    // only ever bump into an enemy, not anyone else in the way
+    steps.iter().copied().find(|&s| {
+        s != Point(0, 0)
+            && match level.monster_at(pos + s) {
+                Some(i) => mon.hostile(&level.monsters[i]),
+                None => level.passable(pos + s),
+            }
+    })
+}
@@ -1,19 +1,148 @@
+use rand::distributions::{Distribution, Uniform};
+use rand::RngCore;
 use serde_derive::Deserialize;
 
+/// Attacks roll 1d`HIT_DIE` plus their to-hit and land if that beats this plus the defense
+pub const BASE_DEFENSE: i32 = 5;
+pub const HIT_DIE: i32 = 20;
+
+/// How an attack roll turned out
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Hit {
+    Miss,
+    Hit,
+    /// a natural `HIT_DIE`, which always hits and rolls the damage twice
+    Crit,
+}
+
+/// Rolls to hit. A natural 1 always misses and a natural `HIT_DIE` always crits.
+pub fn roll_to_hit<R: RngCore>(to_hit: i32, defense: i32, rng: &mut R) -> Hit {
+    let roll = Uniform::new_inclusive(1, HIT_DIE).sample(rng);
+    if roll == HIT_DIE {
+        Hit::Crit
+    } else if roll > 1 && roll + to_hit > BASE_DEFENSE + defense {
+        Hit::Hit
+    } else {
+        Hit::Miss
+    }
+}
+
+/// Damage after the defender's `damage_taken` multiplier for its class, rounded to nearest
+pub fn scale_damage(roll: i32, mult: f64) -> i32 {
+    (roll as f64 * mult).round() as i32
+}
+
+// all of the text here is a template, see `message::fill`
+
 #[derive(Debug, Deserialize)]
 pub struct DamageInfo {
     pub name: String,
     pub attacks: Vec<AttackFlavor>,
     pub deaths: Vec<DeathFlavor>,
+    pub misses: Vec<AttackFlavor>,
+    /// said of the attacker when they land a critical hit
+    pub critical: String,
+    /// said of the defender when they take less damage from this class
+    pub resisted: String,
+    /// said of the defender when they take none at all
+    pub immune: String,
+    /// said of the defender when they take extra damage
+    pub vulnerable: String,
+}
+impl DamageInfo {
+    /// What to say about the defender taking damage with this `damage_taken` multiplier, if
+    /// anything
+    pub fn taken(&self, mult: f64) -> Option<&str> {
+        if mult <= 0.0 {
+            Some(&self.immune)
+        } else if mult < 1.0 {
+            Some(&self.resisted)
+        } else if mult > 1.0 {
+            Some(&self.vulnerable)
+        } else {
+            None
+        }
+    }
 }
 #[derive(Clone, Debug, Deserialize)]
 pub struct AttackFlavor {
-    pub player: (String, String),
-    pub monster_m: (String, String),
-    pub monster_p: (String, String),
+    /// the player hitting a monster
+    pub player: String,
+    /// a monster hitting another monster
+    pub monster_m: String,
+    /// a monster hitting the player
+    pub monster_p: String,
+}
+impl AttackFlavor {
+    /// The template for an attack by `attacker` on `defender`, by their indices in the level
+    pub fn template(&self, attacker: usize, defender: usize) -> &str {
+        if attacker == 0 {
+            &self.player
+        } else if defender == 0 {
+            &self.monster_p
+        } else {
+            &self.monster_m
+        }
+    }
 }
 #[derive(Debug, Deserialize)]
 pub struct DeathFlavor {
     pub player: String,
     pub monster: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::mock::StepRng;
+
+    // rngs that make the d20 come up 1, 11 and 20
+    fn natural(roll: u64) -> StepRng {
+        match roll {
+            1 => StepRng::new(0, 0),
+            11 => StepRng::new(1 << 31, 0),
+            20 => StepRng::new(u64::MAX, 0),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn hit_roll_beats_defense() {
+        assert_eq!(roll_to_hit(0, 5, &mut natural(11)), Hit::Hit);
+        assert_eq!(roll_to_hit(0, 6, &mut natural(11)), Hit::Miss);
+        assert_eq!(roll_to_hit(1, 6, &mut natural(11)), Hit::Hit);
+    }
+
+    #[test]
+    fn natural_rolls() {
+        assert_eq!(roll_to_hit(100, 0, &mut natural(1)), Hit::Miss);
+        assert_eq!(roll_to_hit(-100, 100, &mut natural(20)), Hit::Crit);
+    }
+
+    #[test]
+    fn damage_multipliers() {
+        let flavor = |s: &str| AttackFlavor {
+            player: s.to_owned(),
+            monster_m: s.to_owned(),
+            monster_p: s.to_owned(),
+        };
+        let info = DamageInfo {
+            name: "test".to_owned(),
+            attacks: vec![flavor("hit")],
+            deaths: Vec::new(),
+            misses: vec![flavor("miss")],
+            critical: "crit".to_owned(),
+            resisted: "resisted".to_owned(),
+            immune: "immune".to_owned(),
+            vulnerable: "vulnerable".to_owned(),
+        };
+        assert_eq!(scale_damage(4, 0.0), 0);
+        assert_eq!(info.taken(0.0), Some("immune"));
+        assert_eq!(scale_damage(5, 0.5), 3);
+        assert_eq!(info.taken(0.5), Some("resisted"));
+        assert_eq!(scale_damage(5, 1.0), 5);
+        assert_eq!(info.taken(1.0), None);
+        assert_eq!(scale_damage(3, 2.0), 6);
+        assert_eq!(info.taken(2.0), Some("vulnerable"));
+    }
+}
@@ -0,0 +1,170 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Something that lasts on a creature for a few turns
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    /// loses `power` health every turn
+    Poison,
+    /// loses its turns
+    Stun,
+    /// moves at half speed
+    Slow,
+    /// moves at double speed
+    Haste,
+    /// stumbles in a random direction half the time
+    Confusion,
+}
+impl EffectKind {
+    /// message templates for when the effect starts and when it wears off
+    pub fn text(self) -> (&'static str, &'static str) {
+        match self {
+            EffectKind::Poison => (
+                "{subject} {feel|looks} sick",
+                "{subject} {feel|looks} better",
+            ),
+            EffectKind::Stun => (
+                "{subject} {are|is} stunned",
+                "{subject} {shake|shakes} it off",
+            ),
+            EffectKind::Slow => (
+                "{subject} {slow|slows} down",
+                "{subject} {speed|speeds} back up",
+            ),
+            EffectKind::Haste => (
+                "{subject} {speed|speeds} up",
+                "{subject} {slow|slows} back down",
+            ),
+            EffectKind::Confusion => (
+                "{subject} {are|is} confused",
+                "{subject} {are|is} no longer confused",
+            ),
+        }
+    }
+    /// shown next to the player's health while it lasts
+    pub fn name(self) -> &'static str {
+        match self {
+            EffectKind::Poison => "poisoned",
+            EffectKind::Stun => "stunned",
+            EffectKind::Slow => "slow",
+            EffectKind::Haste => "hasted",
+            EffectKind::Confusion => "confused",
+        }
+    }
+}
+
+/// An effect an attack or item can cause, from monsters.toml or items.toml
+#[derive(Clone, Debug, Deserialize)]
+pub struct EffectInfo {
+    pub kind: EffectKind,
+    pub turns: u32,
+    /// how strong the effect is, only poison uses it
+    #[serde(default = "one")]
+    pub power: i32,
+    /// chance of the effect happening on each hit
+    #[serde(default = "always")]
+    pub chance: f64,
+}
+fn one() -> i32 {
+    1
+}
+fn always() -> f64 {
+    1.0
+}
+
+/// An effect currently on a creature
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Effect {
+    pub kind: EffectKind,
+    /// turns left before it wears off
+    pub turns: u32,
+    pub power: i32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Effects {
+    pub active: Vec<Effect>,
+}
+impl Effects {
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.active.iter().any(|e| e.kind == kind)
+    }
+    /// Starts an effect, or makes one already going last longer. Returns whether it's new.
+    pub fn add(&mut self, info: &EffectInfo) -> bool {
+        match self.active.iter_mut().find(|e| e.kind == info.kind) {
+            Some(e) => {
+                e.turns = e.turns.max(info.turns);
+                e.power = e.power.max(info.power);
+                false
+            }
+            None => {
+                self.active.push(Effect {
+                    kind: info.kind,
+                    turns: info.turns,
+                    power: info.power,
+                });
+                true
+            }
+        }
+    }
+    /// Counts down every effect by a turn, returning the ones that wore off.
+    pub fn tick(&mut self) -> Vec<EffectKind> {
+        let mut expired = Vec::new();
+        self.active.retain(|e| {
+            if e.turns <= 1 {
+                expired.push(e.kind);
+                false
+            } else {
+                true
+            }
+        });
+        for e in &mut self.active {
+            e.turns -= 1;
+        }
+        expired
+    }
+    /// `speed` changed by any slow or haste
+    pub fn speed(&self, speed: i32) -> i32 {
+        let mut speed = speed;
+        if self.has(EffectKind::Slow) {
+            speed /= 2;
+        }
+        if self.has(EffectKind::Haste) {
+            speed *= 2;
+        }
+        speed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(kind: EffectKind, turns: u32) -> EffectInfo {
+        EffectInfo {
+            kind,
+            turns,
+            power: 1,
+            chance: 1.0,
+        }
+    }
+
+    #[test]
+    fn effects_wear_off() {
+        let mut effects = Effects::default();
+        assert!(effects.add(&info(EffectKind::Slow, 2)));
+        assert_eq!(effects.speed(100), 50);
+        assert!(effects.tick().is_empty());
+        assert_eq!(effects.tick(), vec![EffectKind::Slow]);
+        assert_eq!(effects.speed(100), 100);
+    }
+
+    #[test]
+    fn effects_extend() {
+        let mut effects = Effects::default();
+        effects.add(&info(EffectKind::Stun, 1));
+        assert!(!effects.add(&info(EffectKind::Stun, 3)));
+        assert_eq!(effects.active.len(), 1);
+        assert_eq!(effects.active[0].turns, 3);
+    }
+}
@@ -3,30 +3,83 @@ use std::ops::{Index, IndexMut};
 use std::rc::Rc;
 
 use doryen_extra::random::{Dice, MersenneTwister, Random};
-use rand::distributions::{Distribution, Uniform};
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
 use serde::Deserialize;
 
+use crate::ai::{self, Behavior};
 use crate::combat::DamageInfo;
-use crate::map::{gen::Hallways, Level, MapInfo};
-use crate::monster::{Attack, Monster, MonsterInfo};
+use crate::effect::EffectKind;
+use crate::item::{Category, Item, ItemInfo, Slot};
+use crate::map::gen::{self, connect, GeneratorInfo};
+use crate::map::{Level, MapInfo, Stairs};
+use crate::message::{fill, Pronoun};
+use crate::monster::{self, Attack, Monster, MonsterInfo};
 use crate::point::Point;
 use crate::screen::Screen;
 use crate::tile::Tile;
 use crate::util::insert_at_zero;
 
+/// Energy a creature spends to take one action. A creature with speed equal to this acts once
+/// per player turn at normal speed.
+pub const ACTION_COST: i32 = 100;
+
+/// How heavy every corpse is
+const CORPSE_WEIGHT: f64 = 10.0;
+
+/// Most turns a single rest can last, for when the player can't heal
+const MAX_REST: u32 = 1000;
+
+/// Most inventory slots the player can have, one for each letter
+const MAX_SLOTS: usize = 26;
+
 pub struct Game {
     pub info: GameInfo,
     pub menu: Rc<dyn Screen>,
     pub help: Rc<dyn Screen>,
     pub messages: VecDeque<String>,
     pub levels: Levels,
+    /// shared by the player wherever they go, so they can be told apart from other monsters
+    pub player_info: Rc<MonsterInfo>,
+    /// number of player turns taken so far
+    pub turn: u64,
+    /// turned off while watching a replay, so it doesn't clobber or depend on the real save
+    pub use_saves: bool,
+    /// whether a save has been loaded, which means the run can't be replayed from its seed
+    pub from_save: bool,
     pub map_rng: Random<MersenneTwister>,
     pub play_rng: Random<MersenneTwister>,
 }
 
 impl Game {
     pub fn new(info: GameInfo, menu: Rc<dyn Screen>, help: Rc<dyn Screen>, seed: u64) -> Game {
+        let player_info = Rc::new(MonsterInfo {
+            weight: 0.0,
+            name: "player".to_owned(),
+            tile: info.settings.player.tile,
+            attacks: vec![Attack {
+                dam: Dice::new("1d6"),
+                class: "cringe".to_owned(),
+                to_hit: 0,
+                range: None,
+                effects: Vec::new(),
+                text: None,
+            }],
+            health: info.settings.player.health,
+            xp: 0,
+            regen: info.settings.player.regen,
+            armor: 0,
+            to_hit: 0,
+            defense: 0,
+            damage_taken: HashMap::new(),
+            speed: info.settings.player.speed,
+            fov: info.settings.player.fov,
+            friendly: true,
+            faction: None,
+            corpse: None,
+            behavior: Behavior::Still,
+            proper: false,
+            pronoun: Pronoun::You,
+        });
         let mut game = Game {
             info,
             menu,
@@ -37,51 +90,465 @@ impl Game {
                 floors: Vec::new(),
                 basement: Vec::new(),
             },
+            player_info,
+            turn: 0,
+            use_saves: true,
+            from_save: false,
             map_rng: SeedableRng::seed_from_u64(seed),
             play_rng: SeedableRng::seed_from_u64(seed),
         };
-        let mut level = Level::generate(
-            game.info.settings.map.width,
-            game.info.settings.map.height,
-            &mut game,
-            Hallways::new(7, 6),
+        game.start();
+        game
+    }
+
+    /// Generates the first level and puts the player somewhere on it.
+    fn start(&mut self) {
+        let mut level = self.generate_level(0);
+        let pos = gen::random_floor(self, &level)
+            .or_else(|| level.find_tile(|t| t.walkable))
+            .expect("level has nowhere to stand");
+        insert_at_zero(
+            &mut level.monsters,
+            Monster::new(self.player_info.clone(), pos),
         );
-        let px = Uniform::from(0..level.width);
-        let py = Uniform::from(0..level.height);
-        for _ in 0..game.info.settings.map.place_attempts {
-            let x = px.sample(&mut game.map_rng);
-            let y = py.sample(&mut game.map_rng);
-            if level.tiles.get(x, y).walkable {
-                insert_at_zero(
-                    &mut level.monsters,
-                    Monster {
-                        info: Rc::new(MonsterInfo {
-                            weight: 0.0,
-                            name: "player".to_owned(),
-                            tile: game.info.settings.player.tile,
-                            attacks: vec![Attack {
-                                dam: Dice::new("1d6"),
-                                class: "cringe".to_owned(),
-                                text: None,
-                            }],
-                            health: 20,
-                            friendly: true,
-                        }),
-                        hp: 20,
-                        pos: Point(x as i32, y as i32),
-                    },
+        self.levels.add_top(level);
+        self.check_level();
+        self.update_fov();
+    }
+
+    /// In debug builds, warns on stderr if the player can't get everywhere on the level they're
+    /// on. The generator tests are what actually fail on it.
+    fn check_level(&self) {
+        if cfg!(debug_assertions) {
+            let level = self.levels.cur();
+            let lost = connect::unreachable(level, level.monsters[0].pos);
+            if lost > 0 {
+                eprintln!(
+                    "{} tiles on level {} can't be reached",
+                    lost,
+                    self.levels.cur_idx()
                 );
-                break;
             }
         }
+    }
+
+    /// Throws away the current game and starts a fresh one. The new seed comes from the old
+    /// game, so a replay starts the same new game.
+    pub fn reset(&mut self) {
+        let seed = self.play_rng.gen::<u64>();
+        self.map_rng = SeedableRng::seed_from_u64(seed);
+        self.play_rng = SeedableRng::seed_from_u64(seed);
+        self.levels = Levels {
+            level: 0,
+            floors: Vec::new(),
+            basement: Vec::new(),
+        };
+        self.messages.clear();
+        self.turn = 0;
+        self.start();
+    }
+
+    pub fn player_dead(&self) -> bool {
+        self.levels.cur().monsters[0].hp <= 0
+    }
+
+    /// Generates level `leveln` with whichever generator map.toml picks for it, the caller
+    /// decides where it goes.
+    pub fn generate_level(&mut self, leveln: i32) -> Level {
+        let gen = self
+            .info
+            .map
+            .generator_for(leveln)
+            .map(GeneratorInfo::build)
+            // what every level used to be before map.toml picked generators
+            .unwrap_or_else(|| Box::new(gen::Hallways::new(7, 6)));
+        Level::generate(
+            self.info.settings.map.width,
+            self.info.settings.map.height,
+            self,
+            &*gen,
+        )
+    }
+
+    /// Takes the stairs the player is standing on to the next level up or down, generating it
+    /// if nobody has been there yet. Returns whether it took a turn.
+    pub fn take_stairs(&mut self, dir: Stairs) -> bool {
+        let level = self.levels.cur();
+        if level.tile_at(level.monsters[0].pos).and_then(|t| t.stairs) != Some(dir) {
+            self.messages.push_back(match dir {
+                Stairs::Up => "there are no stairs up here".to_owned(),
+                Stairs::Down => "there are no stairs down here".to_owned(),
+            });
+            return false;
+        }
+        let next = self.levels.cur_idx() + dir.delta();
+        let new = !self.levels.contains(next);
+        if new {
+            let level = self.generate_level(next);
+            self.levels.add(next, level);
+        }
+
+        let mut player = self.levels.cur_mut().monsters.swap_remove(0);
+        self.levels.level = next;
+        let level = self.levels.cur_mut();
+        let arrive = level
+            .find_tile(|t| t.stairs == Some(dir.opposite()))
+            .or_else(|| level.find_tile(|t| t.walkable))
+            .expect("level has nowhere to stand");
+        // shove aside anything waiting at the bottom of the stairs
+        if let Some(i) = level.monster_at(arrive) {
+            let free = (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| arrive + Point(x, y)))
+                .find(|&p| {
+                    level.tile_at(p).map_or(false, |t| t.walkable) && level.monster_at(p).is_none()
+                });
+            if let Some(p) = free {
+                level.monsters[i].pos = p;
+            }
+        }
+        player.pos = arrive;
+        insert_at_zero(&mut level.monsters, player);
+        if new {
+            self.check_level();
+        }
+        self.messages.push_back(match dir {
+            Stairs::Up => "you climb up the stairs".to_owned(),
+            Stairs::Down => "you go down the stairs".to_owned(),
+        });
+        true
+    }
+
+    /// Recomputes the player's field of view on the current level.
+    pub fn update_fov(&mut self) {
+        let fov = self.info.settings.player.fov;
+        let level = self.levels.cur_mut();
+        let pos = level.monsters[0].pos;
+        level.tiles.compute_fov(pos.0 as usize, pos.1 as usize, fov);
+    }
+
+    /// Picks up everything the player is standing on that they can carry. Returns whether they
+    /// picked anything up.
+    pub fn pick_up(&mut self) -> bool {
+        let settings = &self.info.settings.player;
+        let level = self.levels.cur_mut();
         let pos = level.monsters[0].pos;
-        game.levels.add_top(level);
-        game.levels.cur_mut().tiles.compute_fov(
-            pos.0 as usize,
-            pos.1 as usize,
-            game.info.settings.player.fov,
+        let mut found = false;
+        let mut picked = false;
+        let mut i = 0;
+        while i < level.items.len() {
+            if level.items[i].0 != pos {
+                i += 1;
+                continue;
+            }
+            found = true;
+            let (_, item) = level.items.remove(i);
+            let (name, count) = (item.name.clone(), item.count);
+            let player = &mut level.monsters[0];
+            match player
+                .inventory
+                .add(item, settings.slots, settings.max_weight)
+            {
+                Some(left) => {
+                    if left.count < count {
+                        picked = true;
+                        self.messages.push_back(format!(
+                            "you pick up {} of the {}",
+                            count - left.count,
+                            name
+                        ));
+                    } else {
+                        self.messages
+                            .push_back(format!("you can't carry the {}", name));
+                    }
+                    level.items.insert(i, (pos, left));
+                    i += 1;
+                }
+                None => {
+                    picked = true;
+                    self.messages.push_back(format!("you pick up the {}", name));
+                }
+            }
+        }
+        if !found {
+            self.messages.push_back("there is nothing here".to_owned());
+        }
+        picked
+    }
+
+    /// Drops the item in slot `idx` of the player's inventory where they are standing.
+    pub fn drop_item(&mut self, idx: usize) {
+        let level = self.levels.cur_mut();
+        let player = &mut level.monsters[0];
+        let item = player.inventory.items.remove(idx);
+        self.messages
+            .push_back(format!("you drop the {}", item.name));
+        level.items.push((player.pos, item));
+    }
+
+    /// Drinks, eats or otherwise uses up one of the item in slot `idx` of the player's
+    /// inventory. Returns whether it took a turn.
+    pub fn use_item(&mut self, idx: usize) -> bool {
+        let level = self.levels.cur_mut();
+        let player = &mut level.monsters[0];
+        let info = player.inventory.items[idx].info.clone();
+        let verb = match info.category {
+            Category::Potion => "drink",
+            Category::Food => "eat",
+            _ if !info.effects.is_empty() => "use",
+            _ => {
+                self.messages
+                    .push_back(format!("you can't use the {}", info.name));
+                return false;
+            }
+        };
+        self.messages
+            .push_back(format!("you {} the {}", verb, info.name));
+        let item = &mut player.inventory.items[idx];
+        item.count -= 1;
+        if item.count == 0 {
+            player.inventory.items.remove(idx);
+        }
+        for effect in &info.effects {
+            if player.effects.add(effect) {
+                self.messages
+                    .push_back(fill(effect.kind.text().0, player.info.noun(), None));
+            }
+        }
+        true
+    }
+
+    /// Equips the item in slot `idx` of the player's inventory, if it goes in `slot`. Whatever
+    /// was there before goes back in the inventory. Returns whether it took a turn.
+    pub fn equip(&mut self, idx: usize, slot: Slot) -> bool {
+        let settings = &self.info.settings.player;
+        let level = self.levels.cur_mut();
+        let player = &mut level.monsters[0];
+        let stack = &mut player.inventory.items[idx];
+        if stack.category.slot() != Some(slot) {
+            self.messages.push_back(match slot {
+                Slot::Weapon => format!("you can't wield the {}", stack.name),
+                Slot::Armor => format!("you can't wear the {}", stack.name),
+            });
+            return false;
+        }
+        let item = if stack.count > 1 {
+            stack.count -= 1;
+            Item::new(stack.info.clone())
+        } else {
+            player.inventory.items.remove(idx)
+        };
+        self.messages.push_back(match slot {
+            Slot::Weapon => format!("you wield the {}", item.name),
+            Slot::Armor => format!("you put on the {}", item.name),
+        });
+        if let Some(old) = player.equipment.get_mut(slot).replace(item) {
+            if let Some(left) = player
+                .inventory
+                .add(old, settings.slots, settings.max_weight)
+            {
+                self.messages
+                    .push_back(format!("you drop the {}", left.name));
+                level.items.push((player.pos, left));
+            }
+        }
+        true
+    }
+
+    /// Takes off whatever the player has in `slot` and puts it in their inventory. Returns
+    /// whether it took a turn.
+    pub fn unequip(&mut self, slot: Slot) -> bool {
+        let settings = &self.info.settings.player;
+        let level = self.levels.cur_mut();
+        let player = &mut level.monsters[0];
+        if let Some(item) = player.equipment.get_mut(slot).take() {
+            self.messages.push_back(match slot {
+                Slot::Weapon => format!("you put away the {}", item.name),
+                Slot::Armor => format!("you take off the {}", item.name),
+            });
+            if let Some(left) = player
+                .inventory
+                .add(item, settings.slots, settings.max_weight)
+            {
+                self.messages
+                    .push_back(format!("you drop the {}", left.name));
+                level.items.push((player.pos, left));
+            }
+            true
+        } else {
+            self.messages.push_back(match slot {
+                Slot::Weapon => "you aren't wielding anything".to_owned(),
+                Slot::Armor => "you aren't wearing anything".to_owned(),
+            });
+            false
+        }
+    }
+
+    /// The attack the player shoots with, if they're wielding something with range
+    pub fn ranged_attack(&self) -> Option<Attack> {
+        self.levels.cur().monsters[0]
+            .equipment
+            .weapon
+            .as_ref()
+            .and_then(|w| w.weapon.clone())
+            .filter(|a| a.range.is_some())
+    }
+
+    /// Shoots the player's ranged weapon towards `to`. Returns whether it took a turn.
+    pub fn fire(&mut self, to: Point) -> bool {
+        let attack = match self.ranged_attack() {
+            Some(attack) => attack,
+            None => return false,
+        };
+        monster::fire(
+            0,
+            to,
+            &attack,
+            self.levels.cur_mut(),
+            &self.info,
+            &mut self.messages,
+            &mut self.play_rng,
         );
-        game
+        true
+    }
+
+    /// Index of a hostile monster the player can see, if there are any
+    pub fn visible_enemy(&self) -> Option<usize> {
+        let level = self.levels.cur();
+        level.monsters.iter().position(|m| {
+            m.hp > 0
+                && level.monsters[0].hostile(m)
+                && level.tiles.is_in_fov(m.pos.0 as usize, m.pos.1 as usize)
+        })
+    }
+
+    /// Passes turns until the player is fully healed, gets hurt, or a monster shows up. If
+    /// they're already healed, just waits a turn.
+    pub fn rest(&mut self) {
+        if let Some(i) = self.visible_enemy() {
+            let noun = self.levels.cur().monsters[i].info.noun();
+            self.messages
+                .push_back(fill("you can't rest with {a subject} nearby", noun, None));
+            return;
+        }
+        let player = &self.levels.cur().monsters[0];
+        if player.hp >= player.max_hp {
+            self.end_turn();
+            return;
+        }
+        for _ in 0..MAX_REST {
+            let hp = self.levels.cur().monsters[0].hp;
+            self.end_turn();
+            let player = &self.levels.cur().monsters[0];
+            if player.hp <= 0 {
+                return;
+            }
+            if player.hp < hp {
+                self.messages
+                    .push_back("you stop resting as you're getting hurt".to_owned());
+                return;
+            }
+            if let Some(i) = self.visible_enemy() {
+                let noun = self.levels.cur().monsters[i].info.noun();
+                self.messages
+                    .push_back(fill("{A subject} comes into view", noun, None));
+                return;
+            }
+            if player.hp >= player.max_hp {
+                self.messages.push_back("you feel rested".to_owned());
+                return;
+            }
+        }
+        self.messages
+            .push_back("you can't seem to get any rest".to_owned());
+    }
+
+    /// Called after the player takes an action. Hands out energy according to each creature's
+    /// speed and lets monsters act until the player has enough energy to move again. A stunned
+    /// player sits out turns until it wears off.
+    pub fn end_turn(&mut self) {
+        loop {
+            self.turn += 1;
+            self.levels.cur_mut().monsters[0].energy -= ACTION_COST;
+            self.update_fov();
+            loop {
+                for idx in 1..self.levels.cur().monsters.len() {
+                    loop {
+                        let mon = &mut self.levels.cur_mut().monsters[idx];
+                        if mon.hp <= 0 || mon.energy < ACTION_COST {
+                            break;
+                        }
+                        mon.energy -= ACTION_COST;
+                        ai::act(idx, self);
+                    }
+                }
+                let level = self.levels.cur_mut();
+                if level.monsters[0].hp <= 0 || level.monsters[0].energy >= ACTION_COST {
+                    break;
+                }
+                for mon in &mut level.monsters {
+                    let speed = mon.speed();
+                    mon.energy += speed;
+                }
+            }
+            let stunned = self.levels.cur().monsters[0].effects.has(EffectKind::Stun);
+            self.tick_creatures();
+            self.levels.cur_mut().remove_dead();
+            // monsters can open doors, which changes what the player can see
+            self.update_fov();
+
+            let autosave = self.info.settings.interface.autosave;
+            if autosave > 0 && self.turn % autosave == 0 {
+                self.autosave();
+            }
+            if !stunned || self.player_dead() {
+                break;
+            }
+        }
+    }
+
+    /// Counts down the effects on every creature on the current level, hurts the poisoned and
+    /// heals everyone else.
+    fn tick_creatures(&mut self) {
+        let turn = self.turn;
+        let level = self.levels.cur_mut();
+        for (i, mon) in level.monsters.iter_mut().enumerate() {
+            if mon.hp <= 0 {
+                continue;
+            }
+            let noun = mon.info.noun();
+            // only tell the player about monsters they can see
+            let seen = i == 0
+                || level
+                    .tiles
+                    .is_in_fov(mon.pos.0 as usize, mon.pos.1 as usize);
+            let poison: i32 = mon
+                .effects
+                .active
+                .iter()
+                .filter(|e| e.kind == EffectKind::Poison)
+                .map(|e| e.power)
+                .sum();
+            mon.hp -= poison;
+            if poison == 0 && mon.regen > 0 && turn % mon.regen == 0 {
+                mon.hp = (mon.hp + 1).min(mon.max_hp);
+            }
+            if mon.hp <= 0 {
+                mon.killed_by = Some("poison".to_owned());
+                if seen {
+                    self.messages.push_back(fill(
+                        "{subject} {succumb|succumbs} to the poison",
+                        noun,
+                        None,
+                    ));
+                }
+                continue;
+            }
+            for kind in mon.effects.tick() {
+                if seen {
+                    self.messages.push_back(fill(kind.text().1, noun, None));
+                }
+            }
+        }
     }
 }
 
@@ -90,27 +557,66 @@ pub struct Levels {
     floors: Vec<Level>,
     basement: Vec<Level>,
 }
+// level 0 is the ground floor, positive levels are floors[level] going up, and negative levels are
+// basement[-level - 1] going down
 impl Levels {
     pub fn add_top(&mut self, level: Level) {
         self.floors.push(level);
     }
-    pub fn cur_idx(&self) -> i32 {
-        self.level
+    pub fn add_bottom(&mut self, level: Level) {
+        self.basement.push(level);
     }
-    pub fn cur(&self) -> &Level {
-        if self.level < 0 {
-            &self.basement[(-self.level + 1) as usize]
+    /// Adds a level at `leveln`, which must be just above the top floor or just below the bottom
+    /// of the basement.
+    pub fn add(&mut self, leveln: i32, level: Level) {
+        if leveln >= 0 {
+            assert_eq!(leveln as usize, self.floors.len());
+            self.add_top(level);
         } else {
-            &self.floors[self.level as usize]
+            assert_eq!((-leveln - 1) as usize, self.basement.len());
+            self.add_bottom(level);
         }
     }
-    pub fn cur_mut(&mut self) -> &mut Level {
-        if self.level < 0 {
-            &mut self.basement[(-self.level + 1) as usize]
+    pub fn contains(&self, leveln: i32) -> bool {
+        if leveln >= 0 {
+            (leveln as usize) < self.floors.len()
+        } else {
+            ((-leveln - 1) as usize) < self.basement.len()
+        }
+    }
+    /// Puts levels back together from a save, checking the current level exists.
+    pub fn from_parts(
+        level: i32,
+        floors: Vec<Level>,
+        basement: Vec<Level>,
+    ) -> Result<Levels, String> {
+        let levels = Levels {
+            level,
+            floors,
+            basement,
+        };
+        if levels.contains(level) {
+            Ok(levels)
         } else {
-            &mut self.floors[self.level as usize]
+            Err(format!("current level {} was not saved", level))
         }
     }
+    pub fn floors(&self) -> &[Level] {
+        &self.floors
+    }
+    pub fn basement(&self) -> &[Level] {
+        &self.basement
+    }
+    pub fn cur_idx(&self) -> i32 {
+        self.level
+    }
+    pub fn cur(&self) -> &Level {
+        &self[self.level]
+    }
+    pub fn cur_mut(&mut self) -> &mut Level {
+        let level = self.level;
+        &mut self[level]
+    }
 }
 impl Index<i32> for Levels {
     type Output = Level;
@@ -137,6 +643,87 @@ pub struct GameInfo {
     pub map: MapInfo,
     pub monster: HashMap<String, Rc<MonsterInfo>>,
     pub damage: HashMap<String, DamageInfo>,
+    pub item: HashMap<String, Rc<ItemInfo>>,
+}
+impl GameInfo {
+    /// Parses the contents of `loader::STATIC_FILES`
+    pub fn parse(files: &[String]) -> GameInfo {
+        let monster: HashMap<String, MonsterInfo> =
+            toml::from_str(&files[2]).expect("Could not parse monsters");
+        let mut item: HashMap<String, Rc<ItemInfo>> =
+            toml::from_str(&files[4]).expect("Could not parse items");
+        for (key, info) in &item {
+            if info.stack == 0 {
+                panic!("items.toml: {} has a stack of 0", key);
+            }
+        }
+        // every monster gets a corpse item, which is never generated on its own
+        let monster: HashMap<String, Rc<MonsterInfo>> = monster
+            .into_iter()
+            .map(|(key, mut info)| {
+                let corpse = Rc::new(ItemInfo {
+                    name: format!("{} corpse", info.name),
+                    tile: Tile {
+                        ch: '%' as u16,
+                        ..info.tile
+                    },
+                    weight: CORPSE_WEIGHT,
+                    stack: 1,
+                    category: Category::Food,
+                    frequency: 0.0,
+                    weapon: None,
+                    armor: None,
+                    effects: Vec::new(),
+                });
+                let corpse_key = format!("{}_corpse", key);
+                if item.contains_key(&corpse_key) {
+                    panic!(
+                        "items.toml: {} is taken by the corpse of {}",
+                        corpse_key, key
+                    );
+                }
+                item.insert(corpse_key, corpse.clone());
+                info.corpse = Some(corpse);
+                (key, Rc::new(info))
+            })
+            .collect();
+        let map: MapInfo = toml::from_str(&files[1]).expect("Could not parse map info");
+        for l in &map.levels {
+            if !map.generators.contains_key(&l.generator) {
+                panic!("map.toml: levels use unknown generator {}", l.generator);
+            }
+        }
+        for (name, room) in &map.rooms {
+            for tile in room.tiles.values() {
+                if !map.tiles.contains_key(tile) {
+                    panic!("map.toml: room {} uses unknown tile {}", name, tile);
+                }
+            }
+            for mon in room.monsters.values() {
+                if !monster.contains_key(mon) {
+                    panic!("map.toml: room {} uses unknown monster {}", name, mon);
+                }
+            }
+            for it in room.items.values() {
+                if !item.contains_key(it) {
+                    panic!("map.toml: room {} uses unknown item {}", name, it);
+                }
+            }
+        }
+        let mut settings: GameSettings =
+            toml::from_str(&files[0]).expect("Could not parse settings");
+        settings.player.slots = settings.player.slots.min(MAX_SLOTS);
+        if settings.player.xp_per_level == 0 {
+            panic!("settings.toml: xp_per_level has to be at least 1");
+        }
+        GameInfo {
+            settings,
+            map,
+            monster,
+            damage: toml::from_str(&files[3]).expect("Could not parse damage"),
+            item,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -151,6 +738,8 @@ pub struct InterfaceSettings {
     pub height: u32,
     pub font: FontSettings,
     pub key_delay: u32,
+    /// save every this many turns, 0 to only save when leaving the game
+    pub autosave: u64,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct FontSettings {
@@ -161,12 +750,72 @@ pub struct FontSettings {
 #[derive(Debug, Deserialize, Clone)]
 pub struct PlayerSettings {
     pub fov: usize,
+    pub speed: i32,
+    pub health: i32,
+    /// turns it takes the player to heal one hp
+    pub regen: u64,
+    /// max health gained each experience level, by the player and monsters alike
+    pub hp_per_level: i32,
+    /// reaching experience level n + 1 takes this times n squared experience, at least 1
+    pub xp_per_level: u32,
+    /// how many different stacks of items the player can carry, at most `MAX_SLOTS`
+    pub slots: usize,
+    pub max_weight: f64,
     pub tile: Tile,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct MapSettings {
     pub place_attempts: i32,
+    /// areas smaller than this that can't be reached are filled in instead of dug out to
+    pub min_region: usize,
     pub num_monsters: u32,
+    pub num_items: u32,
     pub width: usize,
     pub height: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::MapTile;
+
+    // levels told apart by their size
+    fn level(size: usize) -> Level {
+        let wall = MapTile {
+            tile: Tile {
+                ch: '#' as u16,
+                fg: (255, 255, 255, 255),
+                bg: (0, 0, 0, 255),
+            },
+            transparent: false,
+            walkable: false,
+            open: None,
+            close: None,
+            flip: None,
+            stairs: None,
+        };
+        Level::new(size, size, Rc::new(wall))
+    }
+
+    #[test]
+    fn basement_levels_count_down() {
+        let mut levels = Levels {
+            level: 0,
+            floors: Vec::new(),
+            basement: Vec::new(),
+        };
+        levels.add(0, level(1));
+        levels.add(1, level(2));
+        levels.add(-1, level(3));
+        levels.add(-2, level(4));
+        assert_eq!(levels[0].width, 1);
+        assert_eq!(levels[1].width, 2);
+        assert_eq!(levels[-1].width, 3);
+        assert_eq!(levels[-2].width, 4);
+        assert!(levels.contains(-2));
+        assert!(!levels.contains(-3));
+        assert!(!levels.contains(2));
+        levels.level = -2;
+        assert_eq!(levels.cur().width, 4);
+    }
+}
@@ -0,0 +1,381 @@
+use std::rc::Rc;
+
+use doryen_rs::Console;
+
+use crate::game::{Game, GameInfo};
+use crate::loader::{read_all, STATIC_FILES};
+use crate::replay::{Frame, RecordedKey};
+use crate::screen::game::GameScreen;
+use crate::screen::menu::MenuScreen;
+use crate::screen::textbox::TextBox;
+use crate::screen::{Action, WheatleyEngine};
+
+/// Runs the game without a window, so it can be driven by tests or scripts
+pub struct Headless {
+    engine: WheatleyEngine,
+    con: Console,
+}
+
+impl Headless {
+    /// Starts a new game from the files in static/, already on the game screen. Saving is turned
+    /// off so nothing run here can touch the real save.
+    pub fn new(seed: u64) -> Headless {
+        let info = GameInfo::parse(&read_all(&STATIC_FILES));
+        let (w, h) = (
+            info.settings.interface.width,
+            info.settings.interface.height,
+        );
+        let help = Rc::new(TextBox::new(None, String::new(), 10, 10, true));
+        let menu = Rc::new(MenuScreen::new(
+            String::new(),
+            vec![(
+                String::from("Play!"),
+                Action::Push(Rc::new(GameScreen::new())),
+            )],
+        ));
+        let mut engine = WheatleyEngine::new(Game::new(info, menu, help, seed));
+        engine.game_mut().use_saves = false;
+        let mut headless = Headless {
+            engine,
+            con: Console::new(w, h),
+        };
+        headless.press("Enter");
+        headless
+    }
+
+    pub fn game(&self) -> &Game {
+        self.engine.game()
+    }
+    pub fn game_mut(&mut self) -> &mut Game {
+        self.engine.game_mut()
+    }
+    /// How many screens are open, the main menu and game screen make 2
+    pub fn num_screens(&self) -> usize {
+        self.engine.screens().len()
+    }
+
+    /// Presses a key for a single update, the same as tapping it. Names are the ones screens
+    /// match on, and can be prefixed with `S-`, `C-` or `A-` for shift, ctrl or alt, so
+    /// `S-Period` goes down stairs.
+    pub fn press(&mut self, key: &str) {
+        let mut key = RecordedKey {
+            key: key.to_owned(),
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        loop {
+            if key.key.starts_with("S-") {
+                key.shift = true;
+            } else if key.key.starts_with("C-") {
+                key.ctrl = true;
+            } else if key.key.starts_with("A-") {
+                key.alt = true;
+            } else {
+                break;
+            }
+            key.key.replace_range(..2, "");
+        }
+        let frame = Frame {
+            tick: 0,
+            held: vec![key.key.clone()],
+            pressed: vec![key],
+        };
+        self.engine.step(&frame);
+    }
+    /// Presses each whitespace separated key in `script` in turn
+    pub fn run(&mut self, script: &str) {
+        for key in script.split_whitespace() {
+            self.press(key);
+        }
+    }
+
+    /// Renders the open screens and returns the console as text, one line per row. Anything
+    /// outside of printable ascii comes out as `?`.
+    pub fn dump(&mut self) -> String {
+        self.engine.render_to(&mut self.con);
+        let (w, h) = (self.con.get_width() as i32, self.con.get_height() as i32);
+        let mut out = String::with_capacity(((w + 1) * h) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                out.push(match self.con.get_ascii(x, y).unwrap_or(0) {
+                    0 => ' ',
+                    c @ 0x20..=0x7e => c as u8 as char,
+                    _ => '?',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Behavior;
+    use crate::effect::{EffectInfo, EffectKind};
+    use crate::map::gen::connect::{connect, unreachable};
+    use crate::map::gen::{Bsp, Generator};
+    use crate::map::Level;
+    use crate::monster::{move_to, Monster, MonsterInfo};
+    use crate::point::Point;
+    use crate::save;
+
+    // a key that moves the player somewhere they can go
+    fn open_direction(sim: &Headless) -> &'static str {
+        let level = sim.game().levels.cur();
+        let pos = level.monsters[0].pos;
+        [
+            ("KeyH", Point(-1, 0)),
+            ("KeyL", Point(1, 0)),
+            ("KeyK", Point(0, -1)),
+            ("KeyJ", Point(0, 1)),
+        ]
+        .iter()
+        .find(|(_, d)| level.passable(pos + *d))
+        .map(|(k, _)| *k)
+        .expect("player is boxed in")
+    }
+
+    #[test]
+    fn starts_in_game() {
+        let mut sim = Headless::new(1);
+        assert_eq!(sim.num_screens(), 2);
+        let level = sim.game().levels.cur();
+        let pos = level.monsters[0].pos;
+        assert!(level.tile_at(pos).unwrap().walkable);
+        assert!(sim.dump().contains('@'));
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        let mut a = Headless::new(42);
+        let mut b = Headless::new(42);
+        for _ in 0..20 {
+            let key = open_direction(&a);
+            a.press(key);
+            b.press(key);
+        }
+        assert_eq!(a.game().turn, b.game().turn);
+        assert_eq!(a.dump(), b.dump());
+    }
+
+    #[test]
+    fn moving_takes_a_turn() {
+        let mut sim = Headless::new(7);
+        let key = open_direction(&sim);
+        sim.press(key);
+        assert_eq!(sim.game().turn, 1);
+    }
+
+    #[test]
+    fn inventory_opens_and_closes() {
+        let mut sim = Headless::new(3);
+        sim.press("KeyI");
+        assert_eq!(sim.num_screens(), 3);
+        assert!(sim.dump().contains("Inventory"));
+        sim.press("Escape");
+        assert_eq!(sim.num_screens(), 2);
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let mut a = Headless::new(5);
+        a.run("KeyL KeyJ KeyH KeyK");
+        let save = a.game_mut().save().unwrap();
+        let mut b = Headless::new(6);
+        b.game_mut().load(&save).unwrap();
+        assert_eq!(a.game().turn, b.game().turn);
+        assert_eq!(a.dump(), b.dump());
+        for _ in 0..10 {
+            let key = open_direction(&a);
+            a.press(key);
+            b.press(key);
+        }
+        assert_eq!(a.dump(), b.dump());
+    }
+
+    #[test]
+    fn saving_keeps_games_in_step() {
+        let mut a = Headless::new(12);
+        let mut b = Headless::new(12);
+        a.game_mut().use_saves = true;
+        // nothing should end either run early
+        for sim in &mut [&mut a, &mut b] {
+            let player = &mut sim.game_mut().levels.cur_mut().monsters[0];
+            player.max_hp = 100_000;
+            player.hp = 100_000;
+        }
+        let turns = a.game().info.settings.interface.autosave * 2 + 10;
+        for _ in 0..turns * 2 {
+            if a.game().turn >= turns {
+                break;
+            }
+            let key = open_direction(&a);
+            a.press(key);
+            b.press(key);
+        }
+        a.game_mut().use_saves = false;
+        save::delete();
+        assert!(a.game().turn >= turns);
+        assert_eq!(a.game().turn, b.game().turn);
+        assert_eq!(a.dump(), b.dump());
+    }
+
+    #[test]
+    fn character_sheet_shows_level() {
+        let mut sim = Headless::new(8);
+        sim.game_mut().levels.cur_mut().monsters[0].xp = 19;
+        let settings = sim.game().info.settings.player.clone();
+        let player = &mut sim.game_mut().levels.cur_mut().monsters[0];
+        assert_eq!(player.gain_xp(1, &settings), 1);
+        sim.press("KeyX");
+        assert_eq!(sim.num_screens(), 3);
+        assert!(sim.dump().contains("level: 2"));
+    }
+
+    #[test]
+    fn death_returns_to_menu() {
+        let mut sim = Headless::new(4);
+        sim.game_mut().levels.cur_mut().monsters[0].hp = 0;
+        sim.press("Period");
+        assert_eq!(sim.num_screens(), 2);
+        assert!(sim.dump().contains("GAME OVER"));
+        sim.press("Enter");
+        assert_eq!(sim.num_screens(), 1);
+        sim.press("Enter");
+        assert_eq!(sim.num_screens(), 2);
+        assert!(!sim.game().player_dead());
+        assert_eq!(sim.game().turn, 0);
+    }
+
+    #[test]
+    fn every_generator_connects() {
+        let mut sim = Headless::new(9);
+        let game = sim.game_mut();
+        let mut gens: Vec<_> = game.info.map.generators.clone().into_iter().collect();
+        gens.sort_by(|a, b| a.0.cmp(&b.0));
+        let (width, height) = (game.info.settings.map.width, game.info.settings.map.height);
+        for (name, info) in gens {
+            for _ in 0..3 {
+                let level = Level::generate(width, height, game, &*info.build());
+                let start = level
+                    .find_tile(|t| t.walkable)
+                    .unwrap_or_else(|| panic!("{} made a level with no floor", name));
+                assert_eq!(unreachable(&level, start), 0, "{} left tiles cut off", name);
+            }
+        }
+    }
+
+    #[test]
+    fn connect_handles_floor_on_the_edge() {
+        let mut sim = Headless::new(5);
+        let game = sim.game_mut();
+        let wall = game.info.map.tiles["wall"].clone();
+        let floor = game.info.map.tiles["floor"].clone();
+        let mut level = Level::new(8, 8, wall);
+        for x in 0..3 {
+            for y in 0..3 {
+                level.tiles.set(x, y, floor.clone());
+            }
+        }
+        for x in 5..8 {
+            for y in 5..8 {
+                level.tiles.set(x, y, floor.clone());
+            }
+        }
+        connect(game, &mut level);
+        assert_eq!(unreachable(&level, Point(0, 0)), 0);
+    }
+
+    #[test]
+    fn confused_stumble_into_walls() {
+        let mut sim = Headless::new(4);
+        let game = sim.game_mut();
+        let wall = game.info.map.tiles["wall"].clone();
+        let level = game.levels.cur_mut();
+        let pos = level.monsters[0].pos;
+        level.monsters[0].effects.add(&EffectInfo {
+            kind: EffectKind::Confusion,
+            turns: 5,
+            power: 1,
+            chance: 1.0,
+        });
+        // this rng always stumbles, and always up and to the left
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let wpos = pos + Point(-1, -1);
+        level.tiles.set(wpos.0 as usize, wpos.1 as usize, wall);
+        let moved = move_to(
+            0,
+            Point(1, 0),
+            level,
+            &game.info,
+            &mut game.messages,
+            &mut rng,
+        );
+        assert!(moved);
+        assert_eq!(game.levels.cur().monsters[0].pos, pos);
+        assert_eq!(game.messages.back().unwrap(), "you stumble into the wall");
+    }
+
+    #[test]
+    fn fast_monsters_act_twice_a_turn() {
+        let mut sim = Headless::new(6);
+        let game = sim.game_mut();
+        let mut info = (*game.info.monster["zoomer"]).clone();
+        // stands still out of reach, so all it does on its turns is spend energy
+        info.behavior = Behavior::Still;
+        let level = game.levels.cur_mut();
+        let ppos = level.monsters[0].pos;
+        let far = (0..level.width as i32)
+            .flat_map(|x| (0..level.height as i32).map(move |y| Point(x, y)))
+            .find(|&p| level.passable(p) && (p - ppos).len2() > 9)
+            .unwrap();
+        for &speed in &[200, 50] {
+            let mut mon = Monster::new(
+                Rc::new(MonsterInfo {
+                    speed,
+                    ..info.clone()
+                }),
+                far,
+            );
+            mon.energy = 0;
+            level.monsters.push(mon);
+        }
+        game.end_turn();
+        let level = game.levels.cur();
+        let n = level.monsters.len();
+        // one tick gets the player back to acting, by then the fast one has had 200 energy
+        // to spend and the slow one not enough for anything
+        assert_eq!(level.monsters[n - 2].energy, 0);
+        assert_eq!(level.monsters[n - 1].energy, 50);
+    }
+
+    #[test]
+    fn bsp_rooms_stay_inside_the_map() {
+        let mut sim = Headless::new(8);
+        let game = sim.game_mut();
+        let wall = game.info.map.tiles["wall"].clone();
+        let bsp = Bsp::new(3, 12, 0.3, 2);
+        for &size in &[7, 12, 19, 40] {
+            for _ in 0..5 {
+                let mut level = Level::new(size, size, wall.clone());
+                bsp.generate(game, &mut level);
+                assert!(level.find_tile(|t| t.walkable).is_some());
+                for i in 0..size {
+                    for &(x, y) in &[(i, 0), (i, size - 1), (0, i), (size - 1, i)] {
+                        let tile = level.tiles.get(x, y);
+                        assert!(
+                            !tile.walkable && tile.open.is_none(),
+                            "{} at {}, {}",
+                            size,
+                            x,
+                            y
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
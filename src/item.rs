@@ -0,0 +1,215 @@
+use std::ops::Deref;
+use std::rc::Rc;
+
+use serde_derive::Deserialize;
+
+use crate::effect::EffectInfo;
+use crate::monster::Attack;
+use crate::tile::Tile;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ItemInfo {
+    pub name: String,
+    #[serde(flatten)]
+    pub tile: Tile,
+    /// how heavy a single one of this item is
+    pub weight: f64,
+    /// how many of this item fit in one stack
+    #[serde(default = "one")]
+    pub stack: u32,
+    pub category: Category,
+    /// how often the item is placed by generators, relative to other items
+    #[serde(default = "one_f")]
+    pub frequency: f64,
+    /// the attack used while wielding this item
+    pub weapon: Option<Attack>,
+    /// how much damage wearing this item can absorb
+    pub armor: Option<i32>,
+    /// what using up one of this item does
+    #[serde(default)]
+    pub effects: Vec<EffectInfo>,
+}
+fn one() -> u32 {
+    1
+}
+fn one_f() -> f64 {
+    1.0
+}
+impl Deref for ItemInfo {
+    type Target = Tile;
+    fn deref(&self) -> &Tile {
+        &self.tile
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Weapon,
+    Armor,
+    Potion,
+    Food,
+    Misc,
+}
+impl Category {
+    /// the equipment slot items of this category go in, if they can be equipped
+    pub fn slot(self) -> Option<Slot> {
+        match self {
+            Category::Weapon => Some(Slot::Weapon),
+            Category::Armor => Some(Slot::Armor),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Weapon,
+    Armor,
+}
+
+#[derive(Debug, Default)]
+pub struct Equipment {
+    pub weapon: Option<Item>,
+    pub armor: Option<Item>,
+}
+impl Equipment {
+    pub fn get(&self, slot: Slot) -> Option<&Item> {
+        match slot {
+            Slot::Weapon => self.weapon.as_ref(),
+            Slot::Armor => self.armor.as_ref(),
+        }
+    }
+    pub fn get_mut(&mut self, slot: Slot) -> &mut Option<Item> {
+        match slot {
+            Slot::Weapon => &mut self.weapon,
+            Slot::Armor => &mut self.armor,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub info: Rc<ItemInfo>,
+    pub count: u32,
+}
+impl Item {
+    pub fn new(info: Rc<ItemInfo>) -> Item {
+        Item { info, count: 1 }
+    }
+}
+impl Deref for Item {
+    type Target = ItemInfo;
+    fn deref(&self) -> &ItemInfo {
+        &self.info
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+}
+impl Inventory {
+    pub fn weight(&self) -> f64 {
+        self.items.iter().map(|i| i.weight * i.count as f64).sum()
+    }
+    /// Adds as much of `item` as fits within the given limits, and returns whatever is left over.
+    pub fn add(&mut self, mut item: Item, slots: usize, max_weight: f64) -> Option<Item> {
+        let fits = if item.weight > 0.0 {
+            ((max_weight - self.weight()) / item.weight).max(0.0) as u32
+        } else {
+            item.count
+        };
+        let mut left = item.count.min(fits);
+        let over = item.count - left;
+        for stack in &mut self.items {
+            if left == 0 {
+                break;
+            }
+            if Rc::ptr_eq(&stack.info, &item.info) && stack.count < stack.stack {
+                let n = left.min(stack.stack - stack.count);
+                stack.count += n;
+                left -= n;
+            }
+        }
+        while left > 0 && self.items.len() < slots {
+            let n = left.min(item.stack);
+            self.items.push(Item {
+                info: item.info.clone(),
+                count: n,
+            });
+            left -= n;
+        }
+        item.count = left + over;
+        if item.count > 0 {
+            Some(item)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(weight: f64, stack: u32) -> Rc<ItemInfo> {
+        Rc::new(ItemInfo {
+            name: "thing".to_owned(),
+            tile: Tile {
+                ch: '*' as u16,
+                fg: (255, 255, 255, 255),
+                bg: (0, 0, 0, 255),
+            },
+            weight,
+            stack,
+            category: Category::Misc,
+            frequency: 1.0,
+            weapon: None,
+            armor: None,
+            effects: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn inventory_stacks() {
+        let info = info(1.0, 5);
+        let mut inv = Inventory::default();
+        assert!(inv
+            .add(
+                Item {
+                    info: info.clone(),
+                    count: 3
+                },
+                26,
+                100.0
+            )
+            .is_none());
+        assert!(inv.add(Item { info, count: 4 }, 26, 100.0).is_none());
+        assert_eq!(inv.items.len(), 2);
+        assert_eq!(inv.items[0].count, 5);
+        assert_eq!(inv.items[1].count, 2);
+    }
+    #[test]
+    fn inventory_slots() {
+        let mut inv = Inventory::default();
+        assert!(inv.add(Item::new(info(1.0, 1)), 1, 100.0).is_none());
+        let left = inv.add(Item::new(info(1.0, 1)), 1, 100.0);
+        assert_eq!(left.map(|i| i.count), Some(1));
+        assert_eq!(inv.items.len(), 1);
+    }
+    #[test]
+    fn inventory_weight() {
+        let mut inv = Inventory::default();
+        let left = inv.add(
+            Item {
+                info: info(2.0, 10),
+                count: 4,
+            },
+            26,
+            5.0,
+        );
+        assert_eq!(left.map(|i| i.count), Some(2));
+        assert_eq!(inv.weight(), 4.0);
+    }
+}
@@ -3,13 +3,27 @@ use std::fs;
 #[cfg(target_arch = "wasm32")]
 use stdweb::js;
 
+/// The data files a game is built from, in the order `GameInfo::parse` expects them
+pub const STATIC_FILES: [&str; 5] = [
+    "settings.toml",
+    "map.toml",
+    "monsters.toml",
+    "damage.toml",
+    "items.toml",
+];
+
 #[cfg(not(target_arch = "wasm32"))]
-pub fn load(paths: &[&str], callback: Box<dyn Fn(Vec<String>) -> ()>) {
+pub fn read_all(paths: &[&str]) -> Vec<String> {
     let mut results = Vec::with_capacity(paths.len());
     for path in paths {
         results.push(fs::read_to_string("static/".to_owned() + path).unwrap());
     }
-    callback(results);
+    results
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(paths: &[&str], callback: Box<dyn Fn(Vec<String>) -> ()>) {
+    callback(read_all(paths));
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -19,20 +19,31 @@ macro_rules! console {
 
 use doryen_rs::{App, AppOptions};
 
+pub mod ai;
 pub mod colors;
 pub mod combat;
+pub mod effect;
 pub mod game;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod item;
 pub mod loader;
 pub mod map;
+pub mod message;
 pub mod monster;
 // pub mod player;
 pub mod point;
+pub mod replay;
+pub mod save;
 pub mod screen;
 pub mod tile;
 pub mod util;
 
-use game::{Game, GameInfo, GameSettings};
-use loader::load;
+use game::{Game, GameInfo};
+#[cfg(not(target_arch = "wasm32"))]
+use headless::Headless;
+use loader::{load, STATIC_FILES};
+use replay::Recording;
 use screen::game::GameScreen;
 use screen::menu::MenuScreen;
 use screen::textbox::TextBox;
@@ -49,6 +60,12 @@ fn get_rand() -> u64 {
     rand::random()
 }
 
+fn hash_seed(s: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(s.as_bytes());
+    hasher.finish()
+}
+
 fn main() {
     if cfg!(target_arch = "wasm32") {
         std::panic::set_hook(Box::new(|p| {
@@ -56,17 +73,27 @@ fn main() {
             console!(error, s)
         }));
     }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if env::args().nth(1).as_deref() == Some("--headless") {
+            let path = env::args()
+                .nth(2)
+                .expect("--headless needs a file of keys to press");
+            let script = std::fs::read_to_string(path).expect("Could not read script");
+            let seed = env::args().nth(3).map_or(0, |s| hash_seed(&s));
+            let mut sim = Headless::new(seed);
+            sim.run(&script);
+            print!("{}", sim.dump());
+            return;
+        }
+    }
     println!("{}", std::mem::size_of::<Game>());
 
     load(
-        &["settings.toml", "map.toml", "monsters.toml", "damage.toml"],
+        &STATIC_FILES,
         Box::new(|info| {
-            let settings_info = &info[0];
-            let map_info = &info[1];
-            let monster_info = &info[2];
-            let damage_info = &info[3];
-            let settings: GameSettings =
-                toml::from_str(settings_info).expect("Could not parse settings");
+            let info = GameInfo::parse(&info);
+            let settings = &info.settings;
             let mut app = App::new(AppOptions {
                 console_width: settings.interface.width,
                 console_height: settings.interface.height,
@@ -78,10 +105,17 @@ fn main() {
                 ..AppOptions::default()
             });
 
-            let mut hasher = DefaultHasher::new();
-            let seed: u64 = if let Some(_) = env::args().nth(1).map(|s| hasher.write(s.as_bytes()))
-            {
-                hasher.finish()
+            let replay = if env::args().nth(1).as_deref() == Some("--replay") {
+                let path = env::args().nth(2).expect("--replay needs a file to play");
+                Some(Recording::read(&path).expect("Could not read replay"))
+            } else {
+                None
+            };
+
+            let seed: u64 = if let Some(recording) = &replay {
+                recording.seed()
+            } else if let Some(s) = env::args().nth(1) {
+                hash_seed(&s)
             } else {
                 get_rand()
             };
@@ -94,22 +128,53 @@ Arrow keys: move around
 You can also use numpad or vi-keys:
 7 8 9    y k u
 4 @ 6    h @ l
-1 2 3    b j n"#,
+1 2 3    b j n
+5, . or s - rest until healed
+g or , - pick up items
+d - drop an item
+i - show inventory
+x - show character sheet
+a - use an item
+f - fire a ranged weapon (Tab picks the next target)
+w - wield a weapon, W - wear armor, T - take off armor
+c - close doors
+< > - go up or down stairs"#,
                 ),
                 50,
                 30,
                 true,
             ));
 
+            let mut menu_options = vec![
+                (String::from("Play!"), Action::Push(Rc::new(GameScreen::new()))),
+                (String::from("Help"), Action::Push(help.clone())),
+                (String::from("Credits"), Action::Push(Rc::new(TextBox::new(
+                    Some(String::from("Credits")),
+                    String::from("Game by Paul Maynard\nFlavor text contributed by:\n - Joyce Quach\ncurses_vector tileset by DragonDePlatino"),
+                    50, 20, true
+                )))),
+                (String::from("Quit"), Action::Pop),
+            ];
+            // a replay needs the same menu it was recorded with
+            let has_continue = match &replay {
+                Some(recording) => recording.has_continue,
+                None => save::read().is_some(),
+            };
+            if has_continue {
+                menu_options.insert(
+                    0,
+                    (
+                        String::from("Continue"),
+                        Action::Push(Rc::new(GameScreen::resume())),
+                    ),
+                );
+            }
+
             let engine = WheatleyEngine::new(Game::new(
-                GameInfo {
-                    settings,
-                    map: toml::from_str(map_info).expect("Could not parse map info"),
-                    monster: toml::from_str(monster_info).expect("Could not parse monsters"),
-                    damage: toml::from_str(damage_info).expect("Could not parse damage"),
-                },
-                Rc::new(MenuScreen::new(String::from(
-r#"+-------------------------------------------------------------------------+
+                info,
+                Rc::new(MenuScreen::new(
+                    String::from(
+                        r#"+-------------------------------------------------------------------------+
 |           __          ___                _   _                          |
 |           \ \        / / |              | | | |                         |
 |            \ \  /\  / /| |__   ___  __ _| |_| | ___ _   _               |
@@ -124,18 +189,20 @@ r#"+-------------------------------------------------------------------------+
 |  \___ \| | '_ ` _ \| | | | |/ _` | __/ _ \| '__|   / /| | | |/ /| | | | |
 |  ____) | | | | | | | |_| | | (_| | || (_) | |     / /_| |_| / /_| |_| | |
 | |_____/|_|_| |_| |_|\__,_|_|\__,_|\__\___/|_|    |____|\___/____|\___/  |
-+-------------------------------------------------------------------------+"#),
-                vec![
-                    (String::from("Play!"), Action::Push(Rc::new(GameScreen::new()))),
-                    (String::from("Help"), Action::Push(help.clone())),
-                    (String::from("Credits"), Action::Push(Rc::new(TextBox::new(
-                        Some(String::from("Credits")),
-                        String::from("Game by Paul Maynard\nFlavor text contributed by:\n - Joyce Quach\ncurses_vector tileset by DragonDePlatino"),
-                        50, 20, true
-                    )))),
-                    (String::from("Quit"), Action::Pop),
-                ]
-            )), help, seed));
++-------------------------------------------------------------------------+"#,
+                    ),
+                    menu_options,
+                )),
+                help,
+                seed,
+            ));
+            let engine = match replay {
+                Some(recording) => engine.replay(recording),
+                None if !cfg!(target_arch = "wasm32") => {
+                    engine.record(Recording::new(seed, has_continue))
+                }
+                None => engine,
+            };
 
             app.set_engine(Box::new(engine));
             app.run();
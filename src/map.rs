@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -8,15 +9,36 @@ use serde_derive::Deserialize;
 
 pub mod gen;
 
+use crate::item::{Equipment, Item};
 use crate::monster::Monster;
+use crate::point::Point;
 use crate::tile::Tile;
 use crate::util::Grid;
 use crate::Game;
-use gen::Generator;
+use gen::prefab::RoomInfo;
+use gen::{Generator, GeneratorInfo, LevelGen};
 
 #[derive(Debug, Deserialize)]
 pub struct MapInfo {
     pub tiles: HashMap<String, Rc<MapTile>>,
+    /// generators by name, for `levels` to pick from
+    #[serde(default)]
+    pub generators: HashMap<String, GeneratorInfo>,
+    /// which generator makes each level, the first one that covers it wins
+    #[serde(default)]
+    pub levels: Vec<LevelGen>,
+    /// hand-made rooms that generators can use instead of plain ones
+    #[serde(default)]
+    pub rooms: HashMap<String, RoomInfo>,
+}
+impl MapInfo {
+    /// The generator that builds level `leveln`, if any is set up for it
+    pub fn generator_for(&self, leveln: i32) -> Option<&GeneratorInfo> {
+        self.levels
+            .iter()
+            .find(|l| l.contains(leveln))
+            .and_then(|l| self.generators.get(&l.generator))
+    }
 }
 
 pub struct Level {
@@ -26,15 +48,20 @@ pub struct Level {
     pub seen: Grid<Option<u16>>,
     // player is always at position 0 in active level
     pub monsters: Vec<Monster>,
+    pub items: Vec<(Point, Item)>,
 }
 
 impl Level {
-    pub fn generate<T: Generator>(width: usize, height: usize, game: &mut Game, gen: T) -> Level {
+    /// Lays out a level with `gen`, joins up any parts cut off from the rest, then fills it with
+    /// stairs, monsters and items.
+    pub fn generate(width: usize, height: usize, game: &mut Game, gen: &dyn Generator) -> Level {
         let mut l = Level::new(width, height, game.info.map.tiles["wall"].clone());
         gen.generate(game, &mut l);
+        gen::connect::connect(game, &mut l);
+        gen::populate(game, &mut l);
         l
     }
-    fn new(width: usize, height: usize, tile: Rc<MapTile>) -> Level {
+    pub fn new(width: usize, height: usize, tile: Rc<MapTile>) -> Level {
         let mut fov_data = MapData::new(width, height);
         for x in 0..width {
             for y in 0..width {
@@ -50,8 +77,79 @@ impl Level {
             },
             seen: Grid::new(None, width, height),
             monsters: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+    pub fn tile_at(&self, pos: Point) -> Option<&MapTile> {
+        let (ux, uy) = pos.try_into().ok()?;
+        if ux < self.width && uy < self.height {
+            Some(self.tiles.get(ux, uy))
+        } else {
+            None
+        }
+    }
+    /// whether a creature could move into `pos`, opening a door if there is one
+    pub fn passable(&self, pos: Point) -> bool {
+        self.tile_at(pos)
+            .map_or(false, |t| t.walkable || t.open.is_some())
+    }
+    /// whether there is an unobstructed line from `from` to `to` no longer than `radius`
+    pub fn can_see(&self, from: Point, to: Point, radius: usize) -> bool {
+        let r = radius as i32;
+        (to - from).len2() <= r * r
+            && from
+                .line_to(to)
+                .iter()
+                .skip(1)
+                .take_while(|&&p| p != to)
+                .all(|&p| self.tile_at(p).map_or(false, |t| t.transparent))
+    }
+    /// Tiles a projectile passes through flying from `from` towards `to`, stopping at the first
+    /// creature in the way, just short of anything solid, or after `range` tiles.
+    pub fn projectile_path(&self, from: Point, to: Point, range: usize) -> Vec<Point> {
+        let mut path = Vec::new();
+        for p in from.line_to(to).into_iter().skip(1).take(range) {
+            if !self.tile_at(p).map_or(false, |t| t.walkable) {
+                break;
+            }
+            path.push(p);
+            if self.monster_at(p).is_some() {
+                break;
+            }
+        }
+        path
+    }
+    /// Takes dead monsters off the level, leaving their corpses and everything they carried
+    /// where they fell. The player stays even when dead.
+    pub fn remove_dead(&mut self) {
+        let mut i = 1;
+        while i < self.monsters.len() {
+            if self.monsters[i].hp > 0 {
+                i += 1;
+                continue;
+            }
+            let mon = self.monsters.remove(i);
+            if let Some(corpse) = &mon.info.corpse {
+                self.items.push((mon.pos, Item::new(corpse.clone())));
+            }
+            let pos = mon.pos;
+            let Equipment { weapon, armor } = mon.equipment;
+            for item in mon.inventory.items.into_iter().chain(weapon).chain(armor) {
+                self.items.push((pos, item));
+            }
         }
     }
+    /// position of the first tile matching `pred`, scanning row by row
+    pub fn find_tile<F: Fn(&MapTile) -> bool>(&self, pred: F) -> Option<Point> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .find(|&(x, y)| pred(self.tiles.get(x, y)))
+            .map(|(x, y)| Point(x as i32, y as i32))
+    }
+    /// index of the living monster standing at `pos`, if any
+    pub fn monster_at(&self, pos: Point) -> Option<usize> {
+        self.monsters.iter().position(|m| m.hp > 0 && m.pos == pos)
+    }
 }
 
 pub struct Tiles {
@@ -95,6 +193,29 @@ pub struct MapTile {
     pub open: Option<String>,
     pub close: Option<String>,
     pub flip: Option<String>,
+    pub stairs: Option<Stairs>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stairs {
+    Up,
+    Down,
+}
+impl Stairs {
+    /// which way the stairs go in level numbers
+    pub fn delta(self) -> i32 {
+        match self {
+            Stairs::Up => 1,
+            Stairs::Down => -1,
+        }
+    }
+    pub fn opposite(self) -> Stairs {
+        match self {
+            Stairs::Up => Stairs::Down,
+            Stairs::Down => Stairs::Up,
+        }
+    }
 }
 
 impl Deref for MapTile {
@@ -1,22 +1,166 @@
+use rand::distributions::WeightedIndex;
 use rand::Rng;
+use rand_distr::{Distribution, Uniform};
+use serde_derive::Deserialize;
 
+use crate::item::Item;
 use crate::map::Level;
+use crate::monster::Monster;
+use crate::point::Point;
 use crate::Game;
 
+pub mod bsp;
+pub mod caves;
+pub mod connect;
 pub mod hallways;
+pub mod prefab;
+pub use bsp::Bsp;
+pub use caves::Caves;
 pub use hallways::Hallways;
 
 pub trait Generator {
     fn generate(&self, game: &mut Game, level: &mut Level);
 }
 
+/// A generator and its parameters as written in map.toml, picked by `kind`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GeneratorInfo {
+    Empty,
+    Percent {
+        /// chance of each tile staying a wall
+        walls: f64,
+    },
+    Hallways {
+        /// how many times the map is split by hallways
+        depth: usize,
+        /// smallest a room can be
+        min_room: i32,
+    },
+    Bsp {
+        min_room: usize,
+        max_room: usize,
+        /// how far from the middle the map can be cut, from 0 for anywhere to 0.5 for always
+        /// in half
+        split: f64,
+        /// how wide corridors are
+        corridor: usize,
+    },
+    Caves {
+        /// chance of each tile starting out as a wall
+        walls: f64,
+        /// neighbouring walls it takes for a floor tile to turn into a wall
+        birth: usize,
+        /// neighbouring walls it takes for a wall to stay one
+        survival: usize,
+        /// how many times to smooth out the noise
+        passes: usize,
+    },
+}
+impl GeneratorInfo {
+    pub fn build(&self) -> Box<dyn Generator> {
+        match *self {
+            GeneratorInfo::Empty => Box::new(Empty),
+            GeneratorInfo::Percent { walls } => Box::new(Percent(walls)),
+            GeneratorInfo::Hallways { depth, min_room } => Box::new(Hallways::new(depth, min_room)),
+            GeneratorInfo::Bsp {
+                min_room,
+                max_room,
+                split,
+                corridor,
+            } => Box::new(Bsp::new(min_room, max_room, split, corridor)),
+            GeneratorInfo::Caves {
+                walls,
+                birth,
+                survival,
+                passes,
+            } => Box::new(Caves::new(walls, birth, survival, passes)),
+        }
+    }
+}
+
+/// Which generator builds the levels from `from` to `to`, both inclusive and unbounded if left
+/// out
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelGen {
+    pub generator: String,
+    pub from: Option<i32>,
+    pub to: Option<i32>,
+}
+impl LevelGen {
+    pub fn contains(&self, level: i32) -> bool {
+        self.from.map_or(true, |f| f <= level) && self.to.map_or(true, |t| level <= t)
+    }
+}
+
+/// Scatters monsters and items over the walkable tiles of a freshly generated level.
+pub fn populate(game: &mut Game, level: &mut Level) {
+    for name in &["stairs_up", "stairs_down"] {
+        if let Some(Point(x, y)) = random_floor(game, level) {
+            let stairs = game.info.map.tiles[*name].clone();
+            level.tiles.set(x as usize, y as usize, stairs);
+        }
+    }
+
+    // sorted so the same seed always places the same things
+    let mut mon_names: Vec<String> = game.info.monster.keys().cloned().collect();
+    mon_names.sort();
+    if !mon_names.is_empty() {
+        let dist =
+            WeightedIndex::new(mon_names.iter().map(|n| game.info.monster[n].weight)).unwrap();
+        for _ in 0..game.info.settings.map.num_monsters {
+            if let Some(pos) = random_floor(game, level) {
+                let name = &mon_names[dist.sample(&mut game.map_rng)];
+                let info = game.info.monster[name].clone();
+                level.monsters.push(Monster::new(info, pos));
+            }
+        }
+    }
+
+    // corpses have a frequency of 0, they only turn up when something dies
+    let mut item_names: Vec<String> = game
+        .info
+        .item
+        .iter()
+        .filter(|(_, info)| info.frequency > 0.0)
+        .map(|(name, _)| name.clone())
+        .collect();
+    item_names.sort();
+    if !item_names.is_empty() {
+        let dist =
+            WeightedIndex::new(item_names.iter().map(|n| game.info.item[n].frequency)).unwrap();
+        for _ in 0..game.info.settings.map.num_items {
+            if let Some(pos) = random_floor(game, level) {
+                let name = &item_names[dist.sample(&mut game.map_rng)];
+                let info = game.info.item[name].clone();
+                level.items.push((pos, Item::new(info)));
+            }
+        }
+    }
+}
+
+/// Picks a random walkable tile nobody is standing on, giving up after `place_attempts` tries.
+pub fn random_floor(game: &mut Game, level: &Level) -> Option<Point> {
+    let px = Uniform::from(0..level.width);
+    let py = Uniform::from(0..level.height);
+    for _ in 0..game.info.settings.map.place_attempts {
+        let x = px.sample(&mut game.map_rng);
+        let y = py.sample(&mut game.map_rng);
+        let pos = Point(x as i32, y as i32);
+        if level.tiles.get(x, y).walkable && level.monster_at(pos).is_none() {
+            return Some(pos);
+        }
+    }
+    None
+}
+
 pub struct Empty;
 
 impl Generator for Empty {
     fn generate(&self, game: &mut Game, level: &mut Level) {
         let floor = &game.info.map.tiles["floor"];
         for x in 1..level.width - 1 {
-            for y in 0..level.height - 1 {
+            for y in 1..level.height - 1 {
                 level.tiles.set(x, y, floor.clone());
             }
         }
@@ -28,8 +172,8 @@ pub struct Percent(pub f64);
 impl Generator for Percent {
     fn generate(&self, game: &mut Game, level: &mut Level) {
         let floor = &game.info.map.tiles["floor"];
-        for x in 0..level.width {
-            for y in 0..level.height {
+        for x in 1..level.width - 1 {
+            for y in 1..level.height - 1 {
                 if game.map_rng.gen_bool(1.0 - self.0) {
                     level.tiles.set(x, y, floor.clone());
                 }
@@ -0,0 +1,188 @@
+use rand::Rng;
+use rand_distr::{Distribution, Uniform};
+
+use super::Generator;
+use crate::map::Level;
+use crate::point::Point;
+use crate::Game;
+
+/// Binary space partitioning: the map is cut in two over and over, every piece that's left gets
+/// a rectangular room, and the two halves of each cut are joined with a corridor.
+pub struct Bsp {
+    min_room: usize,
+    max_room: usize,
+    /// how far from the middle a cut can be, 0.5 always cuts in half and 0 anywhere
+    split: f64,
+    corridor: usize,
+}
+impl Bsp {
+    pub fn new(min_room: usize, max_room: usize, split: f64, corridor: usize) -> Bsp {
+        Bsp {
+            min_room,
+            max_room,
+            split,
+            corridor,
+        }
+    }
+}
+
+/// The inside of a room, or a piece of the map including the walls around its room
+#[derive(Copy, Clone, Debug)]
+struct Rect {
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+}
+impl Rect {
+    fn center(&self) -> Point {
+        Point((self.x + self.w / 2) as i32, (self.y + self.h / 2) as i32)
+    }
+    /// Whether a corridor going across (x, y) goes through the room's wall, either from the side
+    /// if `horizontal` or from above or below.
+    fn entrance(&self, x: usize, y: usize, horizontal: bool) -> bool {
+        if horizontal {
+            (x == self.x - 1 || x == self.x + self.w) && (self.y..self.y + self.h).contains(&y)
+        } else {
+            (y == self.y - 1 || y == self.y + self.h) && (self.x..self.x + self.w).contains(&x)
+        }
+    }
+}
+
+impl Generator for Bsp {
+    fn generate(&self, game: &mut Game, level: &mut Level) {
+        let area = Rect {
+            x: 0,
+            y: 0,
+            w: level.width,
+            h: level.height,
+        };
+        let mut rooms = Vec::new();
+        self.split(area, game, level, &mut rooms);
+    }
+}
+
+impl Bsp {
+    /// Fills `area` with rooms, returning one of them for the corridor out of it.
+    fn split(&self, area: Rect, game: &mut Game, level: &mut Level, rooms: &mut Vec<Rect>) -> Rect {
+        let min = self.min_room + 2;
+        let vertical = match (area.w >= 2 * min, area.h >= 2 * min) {
+            (false, false) => return self.room(area, game, level, rooms),
+            (true, false) => true,
+            (false, true) => false,
+            // cut across the long side so pieces don't end up thin
+            (true, true) if area.w * 4 > area.h * 5 => true,
+            (true, true) if area.h * 4 > area.w * 5 => false,
+            (true, true) => game.map_rng.gen(),
+        };
+        let len = if vertical { area.w } else { area.h };
+        let lo = ((len as f64 * self.split) as usize).max(min).min(len - min);
+        let hi = ((len as f64 * (1.0 - self.split)) as usize)
+            .min(len - min)
+            .max(lo);
+        let at = Uniform::new_inclusive(lo, hi).sample(&mut game.map_rng);
+        let (a, b) = if vertical {
+            (
+                Rect { w: at, ..area },
+                Rect {
+                    x: area.x + at,
+                    w: area.w - at,
+                    ..area
+                },
+            )
+        } else {
+            (
+                Rect { h: at, ..area },
+                Rect {
+                    y: area.y + at,
+                    h: area.h - at,
+                    ..area
+                },
+            )
+        };
+        let a = self.split(a, game, level, rooms);
+        let b = self.split(b, game, level, rooms);
+        self.connect(a.center(), b.center(), game, level, rooms);
+        if game.map_rng.gen() {
+            a
+        } else {
+            b
+        }
+    }
+
+    fn room(&self, area: Rect, game: &mut Game, level: &mut Level, rooms: &mut Vec<Rect>) -> Rect {
+        let rng = &mut game.map_rng;
+        let floor = &game.info.map.tiles["floor"];
+        let w = self.room_size(area.w - 2, rng);
+        let h = self.room_size(area.h - 2, rng);
+        let room = Rect {
+            x: area.x + 1 + Uniform::new_inclusive(0, area.w - 2 - w).sample(rng),
+            y: area.y + 1 + Uniform::new_inclusive(0, area.h - 2 - h).sample(rng),
+            w,
+            h,
+        };
+        for x in room.x..room.x + room.w {
+            for y in room.y..room.y + room.h {
+                level.tiles.set(x, y, floor.clone());
+            }
+        }
+        rooms.push(room);
+        room
+    }
+
+    /// A random room width or height that fits in `space`
+    fn room_size<R: Rng>(&self, space: usize, rng: &mut R) -> usize {
+        let max = self.max_room.min(space).max(self.min_room);
+        Uniform::new_inclusive(self.min_room, max)
+            .sample(rng)
+            .min(space)
+    }
+
+    /// Digs an L-shaped corridor between two points.
+    fn connect(&self, from: Point, to: Point, game: &mut Game, level: &mut Level, rooms: &[Rect]) {
+        let corner = if game.map_rng.gen() {
+            Point(to.0, from.1)
+        } else {
+            Point(from.0, to.1)
+        };
+        self.corridor(from, corner, game, level, rooms);
+        self.corridor(corner, to, game, level, rooms);
+    }
+
+    /// Digs a straight corridor, `self.corridor` tiles wide, putting doors where it goes
+    /// through the wall of a room.
+    fn corridor(&self, from: Point, to: Point, game: &Game, level: &mut Level, rooms: &[Rect]) {
+        let door = &game.info.map.tiles["door"];
+        let floor = &game.info.map.tiles["floor"];
+        let horizontal = from.1 == to.1;
+        let (step, side) = if horizontal {
+            (Point((to.0 - from.0).signum(), 0), Point(0, 1))
+        } else {
+            (Point(0, (to.1 - from.1).signum()), Point(1, 0))
+        };
+        let mut p = from;
+        loop {
+            for i in 0..self.corridor as i32 {
+                let Point(x, y) = p + side * i;
+                if x < 1 || y < 1 || x >= level.width as i32 - 1 || y >= level.height as i32 - 1 {
+                    continue;
+                }
+                let (x, y) = (x as usize, y as usize);
+                let tile = level.tiles.get(x, y);
+                // leave rooms and corridors that are already there alone
+                if tile.walkable || tile.open.is_some() {
+                    continue;
+                }
+                if rooms.iter().any(|r| r.entrance(x, y, horizontal)) {
+                    level.tiles.set(x, y, door.clone());
+                } else {
+                    level.tiles.set(x, y, floor.clone());
+                }
+            }
+            if p == to {
+                break;
+            }
+            p += step;
+        }
+    }
+}
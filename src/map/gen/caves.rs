@@ -0,0 +1,71 @@
+use rand::Rng;
+
+use super::Generator;
+use crate::ai::DIRS;
+use crate::map::Level;
+use crate::util::Grid;
+use crate::Game;
+
+/// Caves grown with a cellular automaton: the map starts out as noise like `Percent`, then on
+/// every pass a tile turns to wall when enough of its neighbours are walls. Pockets cut off from
+/// the rest are dealt with by `connect` like on any other level.
+pub struct Caves {
+    walls: f64,
+    birth: usize,
+    survival: usize,
+    passes: usize,
+}
+impl Caves {
+    pub fn new(walls: f64, birth: usize, survival: usize, passes: usize) -> Caves {
+        Caves {
+            walls,
+            birth,
+            survival,
+            passes,
+        }
+    }
+
+    /// One pass of the automaton, the edges of the map always stay wall.
+    fn smooth(&self, walls: &Grid<bool>, width: usize, height: usize) -> Grid<bool> {
+        let mut next = Grid::new(true, width, height);
+        for x in 1..width - 1 {
+            for y in 1..height - 1 {
+                let n = DIRS
+                    .iter()
+                    .filter(|d| walls[[(x as i32 + d.0) as usize, (y as i32 + d.1) as usize]])
+                    .count();
+                next[[x, y]] = if walls[[x, y]] {
+                    n >= self.survival
+                } else {
+                    n >= self.birth
+                };
+            }
+        }
+        next
+    }
+}
+
+impl Generator for Caves {
+    fn generate(&self, game: &mut Game, level: &mut Level) {
+        let (width, height) = (level.width, level.height);
+        let rng = &mut game.map_rng;
+        let mut walls = Grid::new(true, width, height);
+        for x in 1..width - 1 {
+            for y in 1..height - 1 {
+                walls[[x, y]] = rng.gen_bool(self.walls);
+            }
+        }
+        for _ in 0..self.passes {
+            walls = self.smooth(&walls, width, height);
+        }
+
+        let floor = &game.info.map.tiles["floor"];
+        for x in 1..width - 1 {
+            for y in 1..height - 1 {
+                if !walls[[x, y]] {
+                    level.tiles.set(x, y, floor.clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use rand::seq::SliceRandom;
+
+use crate::ai::DIRS;
+use crate::map::Level;
+use crate::point::Point;
+use crate::util::Grid;
+use crate::Game;
+
+/// Makes sure every tile on a freshly generated level can be walked to from every other. Of the
+/// areas cut off from the biggest one, the ones smaller than `min_region` in the map settings
+/// are filled in, along with anything standing in them, and the rest get a tunnel to it.
+pub fn connect(game: &mut Game, level: &mut Level) {
+    let mut areas = regions(&blocked(level), level.width, level.height);
+    areas.sort_by_key(|a| std::cmp::Reverse(a.len()));
+    let (main, rest) = match areas.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let wall = &game.info.map.tiles["wall"];
+    let floor = &game.info.map.tiles["floor"];
+    for area in rest {
+        if area.len() < game.info.settings.map.min_region {
+            for p in area {
+                level.tiles.set(p.0 as usize, p.1 as usize, wall.clone());
+            }
+            level.monsters.retain(|m| !area.contains(&m.pos));
+            level.items.retain(|(p, _)| !area.contains(p));
+        } else {
+            // dig from somewhere in the area to the closest bit of the main one
+            let from = *area.choose(&mut game.map_rng).unwrap();
+            let to = *main.iter().min_by_key(|&&p| (p - from).len2()).unwrap();
+            for p in from.line_to(to) {
+                if !level.passable(p) {
+                    level.tiles.set(p.0 as usize, p.1 as usize, floor.clone());
+                }
+            }
+        }
+    }
+}
+
+/// How many tiles a creature could walk to on the level, but not from `from`
+pub fn unreachable(level: &Level, from: Point) -> usize {
+    let blocked = blocked(level);
+    let mut seen = Grid::new(false, level.width, level.height);
+    let mut stack = vec![from];
+    seen[[from.0 as usize, from.1 as usize]] = true;
+    let mut reached = 0;
+    while let Some(p) = stack.pop() {
+        reached += 1;
+        for &d in &DIRS {
+            let n = p + d;
+            if level.tile_at(n).is_none() {
+                continue;
+            }
+            let (nx, ny) = (n.0 as usize, n.1 as usize);
+            if !blocked[[nx, ny]] && !seen[[nx, ny]] {
+                seen[[nx, ny]] = true;
+                stack.push(n);
+            }
+        }
+    }
+    let open = blocked.elems().iter().filter(|b| !**b).count();
+    open.saturating_sub(reached)
+}
+
+/// Tiles nothing can get through, closed doors don't count
+fn blocked(level: &Level) -> Grid<bool> {
+    let mut blocked = Grid::new(true, level.width, level.height);
+    for x in 0..level.width {
+        for y in 0..level.height {
+            blocked[[x, y]] = !level.passable(Point(x as i32, y as i32));
+        }
+    }
+    blocked
+}
+
+/// Groups the open tiles into areas that can be walked between.
+fn regions(blocked: &Grid<bool>, width: usize, height: usize) -> Vec<Vec<Point>> {
+    let mut seen = Grid::new(false, width, height);
+    let mut areas = Vec::new();
+    for x in 0..width {
+        for y in 0..height {
+            if blocked[[x, y]] || seen[[x, y]] {
+                continue;
+            }
+            seen[[x, y]] = true;
+            let mut area = Vec::new();
+            let mut stack = vec![Point(x as i32, y as i32)];
+            while let Some(p) = stack.pop() {
+                area.push(p);
+                for &d in &DIRS {
+                    let n = p + d;
+                    if n.0 < 0 || n.1 < 0 || n.0 as usize >= width || n.1 as usize >= height {
+                        continue;
+                    }
+                    let (nx, ny) = (n.0 as usize, n.1 as usize);
+                    if !blocked[[nx, ny]] && !seen[[nx, ny]] {
+                        seen[[nx, ny]] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+            areas.push(area);
+        }
+    }
+    areas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Grid<bool> {
+        let width = rows[0].len();
+        Grid::from_vec(rows.concat().chars().map(|c| c == '#').collect(), width)
+    }
+
+    #[test]
+    fn regions_split_on_walls() {
+        let walls = grid(&["#######", "#..#..#", "#..#..#", "#######"]);
+        let areas = regions(&walls, 7, 4);
+        assert_eq!(areas.len(), 2);
+        assert!(areas.iter().all(|a| a.len() == 4));
+    }
+
+    #[test]
+    fn regions_join_diagonally() {
+        let walls = grid(&["#####", "#.###", "##.##", "#####"]);
+        assert_eq!(regions(&walls, 5, 4).len(), 1);
+    }
+
+    #[test]
+    fn regions_reach_the_edge() {
+        let walls = grid(&["..#..", "..#..", "###.."]);
+        let mut areas = regions(&walls, 5, 3);
+        areas.sort_by_key(Vec::len);
+        assert_eq!(areas.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 6]);
+    }
+}
@@ -1,11 +1,13 @@
+use std::iter;
+
 use rand::distributions::WeightedIndex;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use rand_distr::{Distribution, Triangular, Uniform};
 
+use super::prefab::{self, RoomInfo};
 use super::Generator;
 use crate::map::Level;
-use crate::monster::Monster;
 use crate::point::Point;
 use crate::Game;
 
@@ -32,27 +34,6 @@ impl Generator for Hallways {
             level.height as usize - 1,
             game,
         );
-        let px = Uniform::from(0..level.width);
-        let py = Uniform::from(0..level.height);
-        let mon_names: Vec<&String> = game.info.monster.keys().collect();
-        let dist =
-            WeightedIndex::new(mon_names.iter().map(|n| game.info.monster[*n].weight)).unwrap();
-        for _ in 0..game.info.settings.map.num_monsters {
-            for _ in 0..game.info.settings.map.place_attempts {
-                let x = px.sample(&mut game.map_rng);
-                let y = py.sample(&mut game.map_rng);
-                if level.tiles.get(x, y).walkable {
-                    let name = mon_names[dist.sample(&mut game.map_rng)];
-                    let info = game.info.monster[name].clone();
-                    level.monsters.push(Monster {
-                        pos: Point(x as i32, y as i32),
-                        hp: info.health,
-                        info,
-                    });
-                    break;
-                }
-            }
-        }
     }
 }
 
@@ -255,6 +236,9 @@ fn create_room(
     ymax: i32,
     game: &mut Game,
 ) -> (usize, usize) {
+    if let Some(size) = create_prefab(level, x0, y0, dx, dy, xmax, ymax, game) {
+        return size;
+    }
     let rng = &mut game.map_rng;
     let door = &game.info.map.tiles["door"];
     let floor = &game.info.map.tiles["floor"];
@@ -282,6 +266,66 @@ fn create_room(
     (w as usize, h as usize)
 }
 
+/// Puts one of the rooms from map.toml where `create_room` would make a plain one, if any fit
+/// and one gets picked. The room's walls go around the space it takes up, with its top row
+/// against the hallway.
+fn create_prefab(
+    level: &mut Level,
+    x0: usize,
+    y0: usize,
+    dx: (i32, i32),
+    dy: (i32, i32),
+    xmax: i32,
+    ymax: i32,
+    game: &mut Game,
+) -> Option<(usize, usize)> {
+    let (width, height) = (xmax as usize + 2, ymax as usize + 2);
+    let rooms = &game.info.map.rooms;
+    // sorted so the same seed always picks the same rooms
+    let mut names: Vec<&String> = rooms
+        .iter()
+        .filter(|(_, r)| r.fits(width, height))
+        .map(|(n, _)| n)
+        .collect();
+    names.sort();
+    // a plain room is always an option
+    let weights = iter::once(1.0).chain(names.iter().map(|n| rooms[*n].weight));
+    let i = WeightedIndex::new(weights).ok()?.sample(&mut game.map_rng);
+    let room = &rooms[*names.get(i.checked_sub(1)?)?];
+    let layout = room.layout(width, height, &mut game.map_rng);
+    place_prefab(level, x0, y0, dx, dy, room, &layout, game)
+}
+
+/// Stamps `layout` with its walls around the space `create_room` would fill, turned to match
+/// `dx` and `dy`. Returns the size of the space inside the walls.
+fn place_prefab(
+    level: &mut Level,
+    x0: usize,
+    y0: usize,
+    dx: (i32, i32),
+    dy: (i32, i32),
+    room: &RoomInfo,
+    layout: &[Vec<char>],
+    game: &Game,
+) -> Option<(usize, usize)> {
+    let w = layout.first()?.len().checked_sub(2)?;
+    let h = layout.len().checked_sub(2)?;
+    prefab::stamp(
+        game,
+        level,
+        room,
+        layout,
+        |x, y| {
+            Point(
+                x0 as i32 + (x - 1) * dx.0 + (y - 1) * dy.0,
+                y0 as i32 + (x - 1) * dx.1 + (y - 1) * dy.1,
+            )
+        },
+        dx.0 == 0,
+    );
+    Some((w, h))
+}
+
 #[derive(Debug)]
 enum Side {
     Top,
@@ -289,3 +333,85 @@ enum Side {
     Left,
     Right,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::headless::Headless;
+    use crate::map::gen::prefab::Layout;
+
+    #[test]
+    fn prefabs_face_the_hallway() {
+        let sim = Headless::new(1);
+        let game = sim.game();
+        let tiles = &game.info.map.tiles;
+        let legend: HashMap<char, String> = [
+            ('#', "wall"),
+            ('.', "floor"),
+            ('+', "door"),
+            ('|', "blackboard_v"),
+        ]
+        .iter()
+        .map(|&(c, t)| (c, t.to_owned()))
+        .collect();
+        let room = RoomInfo {
+            weight: 1.0,
+            tiles: legend,
+            monsters: HashMap::new(),
+            items: HashMap::new(),
+            layout: Layout::Fixed(String::new()),
+        };
+        let layout: Vec<Vec<char>> = ["#+###", "#..|#", "#####"]
+            .iter()
+            .map(|r| r.chars().collect())
+            .collect();
+        // where each side puts its rooms in `create`
+        let sides = [
+            ((5, 5), (1, 0), (0, 1)),
+            ((5, 14), (1, 0), (0, -1)),
+            ((5, 5), (0, 1), (1, 0)),
+            ((14, 5), (0, 1), (-1, 0)),
+        ];
+        for &((x0, y0), dx, dy) in &sides {
+            let mut level = Level::new(20, 20, tiles["floor"].clone());
+            let size = place_prefab(&mut level, x0, y0, dx, dy, &room, &layout, game);
+            assert_eq!(size, Some((3, 1)));
+            let at = |x: i32, y: i32| {
+                let p = Point(
+                    x0 as i32 + x * dx.0 + y * dy.0,
+                    y0 as i32 + x * dx.1 + y * dy.1,
+                );
+                level.tiles.get_rc(p.0 as usize, p.1 as usize)
+            };
+            // the wall between the room and the hallway
+            assert!(Rc::ptr_eq(&at(0, -1), &tiles["door"]));
+            let blackboard = if dx.0 == 0 {
+                "blackboard_h"
+            } else {
+                "blackboard_v"
+            };
+            assert!(Rc::ptr_eq(&at(2, 0), &tiles[blackboard]));
+            // nothing outside the room and its walls
+            let inside: Vec<Point> = (-1..=3)
+                .flat_map(|x| (-1..=1).map(move |y| (x, y)))
+                .map(|(x, y)| {
+                    Point(
+                        x0 as i32 + x * dx.0 + y * dy.0,
+                        y0 as i32 + x * dx.1 + y * dy.1,
+                    )
+                })
+                .collect();
+            for x in 0..level.width {
+                for y in 0..level.height {
+                    let p = Point(x as i32, y as i32);
+                    if !inside.contains(&p) {
+                        assert!(Rc::ptr_eq(&level.tiles.get_rc(x, y), &tiles["floor"]));
+                    }
+                }
+            }
+        }
+    }
+}
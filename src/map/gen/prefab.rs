@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand_distr::{Distribution, Uniform};
+use serde_derive::Deserialize;
+
+use crate::item::Item;
+use crate::map::Level;
+use crate::monster::Monster;
+use crate::point::Point;
+use crate::Game;
+
+/// A hand-made room from map.toml. The top row of the layout is the wall facing the hallway, so
+/// that's where its doors go.
+#[derive(Debug, Deserialize)]
+pub struct RoomInfo {
+    /// how likely the room is to be picked, a plain room has a weight of 1
+    pub weight: f64,
+    /// what each character of the layout is, as keys of `MapInfo::tiles`
+    pub tiles: HashMap<char, String>,
+    /// characters that put a monster on the floor, as keys of monsters.toml
+    #[serde(default)]
+    pub monsters: HashMap<char, String>,
+    /// characters that put an item on the floor, as keys of items.toml
+    #[serde(default)]
+    pub items: HashMap<char, String>,
+    pub layout: Layout,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Layout {
+    /// drawn out in full, walls included
+    Fixed(String),
+    /// corners and edges with a middle that repeat to fill whatever size the room ends up
+    Stretch(Stretch),
+}
+
+/// A layout cut into nine pieces like ul u ur / l c r / dl d dr. The edges and the middle are
+/// repeated as often as they need to be.
+#[derive(Debug, Deserialize)]
+pub struct Stretch {
+    pub min_width: usize,
+    pub min_height: usize,
+    pub max_width: usize,
+    pub max_height: usize,
+    pub ul: String,
+    pub u: String,
+    pub ur: String,
+    pub l: String,
+    pub c: String,
+    pub r: String,
+    pub dl: String,
+    pub d: String,
+    pub dr: String,
+}
+
+impl RoomInfo {
+    /// Whether the room fits in `width` by `height`, walls included
+    pub fn fits(&self, width: usize, height: usize) -> bool {
+        match &self.layout {
+            Layout::Fixed(layout) => {
+                let rows = rows(layout);
+                rows.len() <= height && rows.iter().all(|r| r.len() <= width)
+            }
+            Layout::Stretch(s) => s.min_width <= width && s.min_height <= height,
+        }
+    }
+
+    /// Lays the room out no bigger than `width` by `height`, walls included, mirrored half the
+    /// time. Every row comes out the same length.
+    pub fn layout<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Vec<Vec<char>> {
+        let mut rows = match &self.layout {
+            Layout::Fixed(layout) => rows(layout),
+            Layout::Stretch(s) => s.layout(width, height, rng),
+        };
+        let len = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize(len, ' ');
+        }
+        if rng.gen() {
+            for row in &mut rows {
+                row.reverse();
+            }
+        }
+        rows
+    }
+}
+
+impl Stretch {
+    fn layout<R: Rng>(&self, width: usize, height: usize, rng: &mut R) -> Vec<Vec<char>> {
+        let w = Uniform::new_inclusive(
+            self.min_width,
+            self.max_width.min(width).max(self.min_width),
+        )
+        .sample(rng);
+        let h = Uniform::new_inclusive(
+            self.min_height,
+            self.max_height.min(height).max(self.min_height),
+        )
+        .sample(rng);
+        let pieces = [
+            [rows(&self.ul), rows(&self.u), rows(&self.ur)],
+            [rows(&self.l), rows(&self.c), rows(&self.r)],
+            [rows(&self.dl), rows(&self.d), rows(&self.dr)],
+        ];
+        let left = pieces[0][0].first().map_or(0, Vec::len);
+        let right = pieces[0][2].first().map_or(0, Vec::len);
+        let top = pieces[0][0].len();
+        let bottom = pieces[2][0].len();
+        // which piece a coordinate falls in, and where in that piece
+        let band = |i: usize, len: usize, start: usize, end: usize| {
+            if i < start {
+                (0, i)
+            } else if i + end >= len {
+                (2, i + end - len)
+            } else {
+                (1, i - start)
+            }
+        };
+        (0..h)
+            .map(|y| {
+                let (py, ly) = band(y, h, top, bottom);
+                (0..w)
+                    .map(|x| {
+                        let (px, lx) = band(x, w, left, right);
+                        let piece = &pieces[py][px];
+                        if piece.is_empty() {
+                            return ' ';
+                        }
+                        let row = &piece[ly % piece.len()];
+                        if row.is_empty() {
+                            ' '
+                        } else {
+                            row[lx % row.len()]
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn rows(layout: &str) -> Vec<Vec<char>> {
+    layout
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(|l| l.chars().collect())
+        .collect()
+}
+
+/// Copies a laid out room onto the level. `to_level` turns a position in the layout into one on
+/// the level, and `turned` says whether that swaps rows for columns, in which case tiles with
+/// a `flip` are swapped for it. Characters not in the legend leave the level as it is.
+pub fn stamp<F: Fn(i32, i32) -> Point>(
+    game: &Game,
+    level: &mut Level,
+    room: &RoomInfo,
+    layout: &[Vec<char>],
+    to_level: F,
+    turned: bool,
+) {
+    let tiles = &game.info.map.tiles;
+    for (y, row) in layout.iter().enumerate() {
+        for (x, ch) in row.iter().enumerate() {
+            let pos = to_level(x as i32, y as i32);
+            // never break through the edge of the map
+            if pos.0 < 1
+                || pos.1 < 1
+                || pos.0 >= level.width as i32 - 1
+                || pos.1 >= level.height as i32 - 1
+            {
+                continue;
+            }
+            let (ux, uy) = (pos.0 as usize, pos.1 as usize);
+            if let Some(name) = room.monsters.get(ch) {
+                level.tiles.set(ux, uy, tiles["floor"].clone());
+                let info = game.info.monster[name].clone();
+                level.monsters.push(Monster::new(info, pos));
+            } else if let Some(name) = room.items.get(ch) {
+                level.tiles.set(ux, uy, tiles["floor"].clone());
+                let info = game.info.item[name].clone();
+                level.items.push((pos, Item::new(info)));
+            } else if let Some(name) = room.tiles.get(ch) {
+                let mut tile = &tiles[name];
+                if turned {
+                    if let Some(flip) = &tile.flip {
+                        tile = &tiles[flip];
+                    }
+                }
+                level.tiles.set(ux, uy, tile.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stretch() -> Stretch {
+        Stretch {
+            min_width: 5,
+            min_height: 4,
+            max_width: 5,
+            max_height: 4,
+            ul: "#".to_owned(),
+            u: "#+".to_owned(),
+            ur: "#".to_owned(),
+            l: "#".to_owned(),
+            c: ".x".to_owned(),
+            r: "#".to_owned(),
+            dl: "#".to_owned(),
+            d: "#".to_owned(),
+            dr: "#".to_owned(),
+        }
+    }
+
+    #[test]
+    fn stretch_repeats_middle() {
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+        let layout: Vec<String> = stretch()
+            .layout(10, 10, &mut rng)
+            .into_iter()
+            .map(|r| r.into_iter().collect())
+            .collect();
+        assert_eq!(layout, vec!["##+##", "#.x.#", "#.x.#", "#####"]);
+    }
+
+    #[test]
+    fn fixed_rows_skip_blank_lines() {
+        assert_eq!(
+            rows("\n#+#\n#.#\n"),
+            vec![vec!['#', '+', '#'], vec!['#', '.', '#']]
+        );
+    }
+}
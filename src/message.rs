@@ -0,0 +1,219 @@
+use serde_derive::Deserialize;
+
+/// How to refer to someone once their name has been used
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pronoun {
+    /// only for the player, who is always "you" no matter their name
+    You,
+    It,
+    He,
+    She,
+    They,
+}
+impl Default for Pronoun {
+    fn default() -> Pronoun {
+        Pronoun::They
+    }
+}
+impl Pronoun {
+    pub fn subject(self) -> &'static str {
+        match self {
+            Pronoun::You => "you",
+            Pronoun::It => "it",
+            Pronoun::He => "he",
+            Pronoun::She => "she",
+            Pronoun::They => "they",
+        }
+    }
+    pub fn object(self) -> &'static str {
+        match self {
+            Pronoun::You => "you",
+            Pronoun::It => "it",
+            Pronoun::He => "him",
+            Pronoun::She => "her",
+            Pronoun::They => "them",
+        }
+    }
+    pub fn possessive(self) -> &'static str {
+        match self {
+            Pronoun::You => "your",
+            Pronoun::It => "its",
+            Pronoun::He => "his",
+            Pronoun::She => "her",
+            Pronoun::They => "their",
+        }
+    }
+}
+
+/// Someone or something that shows up in a message
+#[derive(Copy, Clone, Debug)]
+pub struct Noun<'a> {
+    pub name: &'a str,
+    /// proper names are never given an article
+    pub proper: bool,
+    pub pronoun: Pronoun,
+}
+impl<'a> Noun<'a> {
+    /// "the zoomer", or "you" for the player
+    pub fn the(&self) -> String {
+        if self.pronoun == Pronoun::You {
+            "you".to_owned()
+        } else if self.proper {
+            self.name.to_owned()
+        } else {
+            format!("the {}", self.name)
+        }
+    }
+    /// "a zoomer", "an energy drink", or "you" for the player
+    pub fn a(&self) -> String {
+        if self.pronoun == Pronoun::You {
+            "you".to_owned()
+        } else if self.proper {
+            self.name.to_owned()
+        } else if self.name.starts_with(|c| "aeiouAEIOU".contains(c)) {
+            format!("an {}", self.name)
+        } else {
+            format!("a {}", self.name)
+        }
+    }
+}
+
+/// Fills in a message template from damage.toml.
+///
+/// - `{subject}` and `{object}` become "you", "the zoomer" or a proper name, and `{a subject}`
+///   or `{a object}` use "a" instead
+/// - `{they}`, `{them}` and `{their}` are the subject's pronouns
+/// - `{dab|dabs}` picks the first form when the subject is "you" and the second otherwise
+///
+/// Starting a placeholder with a capital letter capitalizes what it becomes, so `{Subject}`
+/// gives "The zoomer". Anything unrecognized is left as it is so mistakes are easy to spot.
+pub fn fill(template: &str, subject: Noun, object: Option<Noun>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        out += &rest[..start];
+        let key = &rest[start + 1..end];
+        match expand(key, subject, object) {
+            Some(word) => {
+                if key.starts_with(char::is_uppercase) {
+                    out += &capitalize(&word);
+                } else {
+                    out += &word;
+                }
+            }
+            None => out += &rest[start..=end],
+        }
+        rest = &rest[end + 1..];
+    }
+    out + rest
+}
+
+fn expand(key: &str, subject: Noun, object: Option<Noun>) -> Option<String> {
+    if let Some(bar) = key.find('|') {
+        return Some(if subject.pronoun == Pronoun::You {
+            key[..bar].to_owned()
+        } else {
+            key[bar + 1..].to_owned()
+        });
+    }
+    Some(match key.to_lowercase().as_str() {
+        "subject" => subject.the(),
+        "a subject" => subject.a(),
+        "object" => object?.the(),
+        "a object" => object?.a(),
+        "they" => subject.pronoun.subject().to_owned(),
+        "them" => subject.pronoun.object().to_owned(),
+        "their" => subject.pronoun.possessive().to_owned(),
+        _ => return None,
+    })
+}
+
+pub fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YOU: Noun = Noun {
+        name: "player",
+        proper: false,
+        pronoun: Pronoun::You,
+    };
+    const ZOOMER: Noun = Noun {
+        name: "zoomer",
+        proper: false,
+        pronoun: Pronoun::They,
+    };
+    const KAREN: Noun = Noun {
+        name: "Karen",
+        proper: true,
+        pronoun: Pronoun::She,
+    };
+
+    const ATTACK: &str = "{Subject} {dab|dabs} on {object}";
+    const DEATH: &str = "{subject} {have|has} failed {their} vibe check";
+
+    #[test]
+    fn player_attacks_monster() {
+        assert_eq!(fill(ATTACK, YOU, Some(ZOOMER)), "You dab on the zoomer");
+        assert_eq!(fill(ATTACK, YOU, Some(KAREN)), "You dab on Karen");
+    }
+
+    #[test]
+    fn monster_attacks_player() {
+        assert_eq!(fill(ATTACK, ZOOMER, Some(YOU)), "The zoomer dabs on you");
+        assert_eq!(
+            fill(DEATH, YOU, Some(ZOOMER)),
+            "you have failed your vibe check"
+        );
+    }
+
+    #[test]
+    fn monster_attacks_monster() {
+        assert_eq!(
+            fill(ATTACK, KAREN, Some(ZOOMER)),
+            "Karen dabs on the zoomer"
+        );
+        assert_eq!(
+            fill(DEATH, ZOOMER, Some(KAREN)),
+            "the zoomer has failed their vibe check"
+        );
+        assert_eq!(fill(DEATH, KAREN, None), "Karen has failed her vibe check");
+    }
+
+    #[test]
+    fn articles() {
+        let drink = Noun {
+            name: "energy drink",
+            proper: false,
+            pronoun: Pronoun::It,
+        };
+        assert_eq!(
+            fill("{a subject} falls over", drink, None),
+            "an energy drink falls over"
+        );
+        assert_eq!(
+            fill("{A subject} shows up", ZOOMER, None),
+            "A zoomer shows up"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_kept() {
+        assert_eq!(
+            fill("{subject} {oops} {object}", ZOOMER, None),
+            "the zoomer {oops} {object}"
+        );
+    }
+}
@@ -1,19 +1,24 @@
 use std::borrow::Borrow;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryInto;
 use std::fmt;
 use std::ops::Deref;
 use std::rc::Rc;
 
 use doryen_extra::random::{Dice, Rng};
+use rand::distributions::{Distribution, Uniform};
 use rand::seq::SliceRandom;
 use rand::RngCore;
 use serde::de::{Deserializer, Error, Visitor};
 use serde_derive::Deserialize;
 
-use crate::combat::AttackFlavor;
-use crate::game::GameInfo;
+use crate::ai::{Awareness, Behavior, DIRS};
+use crate::combat::{roll_to_hit, scale_damage, AttackFlavor, Hit};
+use crate::effect::{EffectInfo, EffectKind, Effects};
+use crate::game::{GameInfo, PlayerSettings, ACTION_COST};
+use crate::item::{Equipment, Inventory, ItemInfo};
 use crate::map::Level;
+use crate::message::{fill, Noun, Pronoun};
 use crate::point::Point;
 use crate::tile::Tile;
 
@@ -24,9 +29,63 @@ pub struct MonsterInfo {
     #[serde(flatten)]
     pub tile: Tile,
     pub health: i32,
+    /// experience for killing one
+    #[serde(default)]
+    pub xp: u32,
+    /// turns it takes to heal one hp, 0 for never
+    #[serde(default)]
+    pub regen: u64,
+    /// energy gained per tick, an action costs `ACTION_COST`
+    #[serde(default = "default_speed")]
+    pub speed: i32,
     pub attacks: Vec<Attack>,
+    /// natural armor, absorbs up to this much damage from each hit
+    #[serde(default)]
+    pub armor: i32,
+    /// added to the to-hit roll of every attack the monster makes
+    #[serde(default)]
+    pub to_hit: i32,
+    /// how hard the monster is to hit
+    #[serde(default)]
+    pub defense: i32,
+    /// multiplies damage taken of each class, 0 for immune, below 1 to resist and above 1 for
+    /// vulnerable. Classes not listed do normal damage.
+    #[serde(default)]
+    pub damage_taken: HashMap<String, f64>,
+    /// how far the monster can see
+    #[serde(default = "default_fov")]
+    pub fov: usize,
+    /// on the player's side
     #[serde(default)]
     pub friendly: bool,
+    /// monsters of different factions fight each other, those without one only fight the
+    /// player's side
+    pub faction: Option<String>,
+    /// left behind when it dies, made for each monster when the game info is loaded
+    #[serde(skip)]
+    pub corpse: Option<Rc<ItemInfo>>,
+    #[serde(default)]
+    pub behavior: Behavior,
+    /// whether `name` is a proper name, which messages never put "the" in front of
+    #[serde(default)]
+    pub proper: bool,
+    #[serde(default)]
+    pub pronoun: Pronoun,
+}
+fn default_speed() -> i32 {
+    ACTION_COST
+}
+fn default_fov() -> usize {
+    8
+}
+impl MonsterInfo {
+    pub fn noun(&self) -> Noun {
+        Noun {
+            name: &self.name,
+            proper: self.proper,
+            pronoun: self.pronoun,
+        }
+    }
 }
 impl Deref for MonsterInfo {
     type Target = Tile;
@@ -40,6 +99,14 @@ pub struct Attack {
     #[serde(deserialize_with = "de_die")]
     pub dam: Dice,
     pub class: String,
+    /// added to the attacker's own to-hit
+    #[serde(default)]
+    pub to_hit: i32,
+    /// how far the attack can be shot, melee attacks have none
+    pub range: Option<usize>,
+    /// effects each hit might cause
+    #[serde(default)]
+    pub effects: Vec<EffectInfo>,
     pub text: Option<Vec<AttackFlavor>>,
 }
 fn de_die<'de, D: Deserializer<'de>>(de: D) -> Result<Dice, D::Error> {
@@ -61,6 +128,94 @@ pub struct Monster {
     pub info: Rc<MonsterInfo>,
     pub pos: Point,
     pub hp: i32,
+    /// starts at `info.health` and grows with experience level
+    pub max_hp: i32,
+    pub xp: u32,
+    pub exp_level: u32,
+    pub energy: i32,
+    pub awareness: Awareness,
+    /// where the monster last saw the player
+    pub target: Option<Point>,
+    pub inventory: Inventory,
+    pub equipment: Equipment,
+    pub effects: Effects,
+    /// how many monsters this one has killed
+    pub kills: u32,
+    /// whatever killed this monster, as in "killed by a zoomer"
+    pub killed_by: Option<String>,
+}
+impl Monster {
+    pub fn new(info: Rc<MonsterInfo>, pos: Point) -> Monster {
+        Monster {
+            hp: info.health,
+            max_hp: info.health,
+            xp: 0,
+            exp_level: 1,
+            // ready to act straight away
+            energy: ACTION_COST,
+            awareness: Awareness::Unaware,
+            target: None,
+            inventory: Inventory::default(),
+            equipment: Equipment::default(),
+            effects: Effects::default(),
+            kills: 0,
+            killed_by: None,
+            pos,
+            info,
+        }
+    }
+    /// energy gained per tick, after any slow or haste. Never below 1, so everyone gets to act
+    /// eventually.
+    pub fn speed(&self) -> i32 {
+        self.effects.speed(self.info.speed).max(1)
+    }
+    /// whether this monster and `other` fight each other
+    pub fn hostile(&self, other: &Monster) -> bool {
+        if self.friendly || other.friendly {
+            return self.friendly != other.friendly;
+        }
+        match (&self.faction, &other.faction) {
+            (Some(a), Some(b)) => a != b,
+            _ => false,
+        }
+    }
+    /// added to every attack roll, better with each experience level
+    pub fn to_hit(&self) -> i32 {
+        self.info.to_hit + self.exp_level as i32 - 1
+    }
+    /// added to the damage of every hit
+    pub fn damage_bonus(&self) -> i32 {
+        (self.exp_level as i32 - 1) / 2
+    }
+    /// total experience needed to reach the next experience level
+    pub fn next_level_xp(&self, settings: &PlayerSettings) -> u32 {
+        settings.xp_per_level.max(1) * self.exp_level * self.exp_level
+    }
+    /// Adds experience, going up as many levels as it's enough for. Returns how many that was.
+    ///
+    /// Monsters that level up from kills go by the player's settings too, there are no
+    /// separate ones for them.
+    pub fn gain_xp(&mut self, xp: u32, settings: &PlayerSettings) -> u32 {
+        self.xp += xp;
+        let mut gained = 0;
+        while self.xp >= self.next_level_xp(settings) {
+            self.exp_level += 1;
+            self.max_hp += settings.hp_per_level;
+            self.hp += settings.hp_per_level;
+            gained += 1;
+        }
+        gained
+    }
+    /// natural armor plus whatever armor the monster is wearing
+    pub fn armor(&self) -> i32 {
+        self.info.armor
+            + self
+                .equipment
+                .armor
+                .as_ref()
+                .and_then(|a| a.armor)
+                .unwrap_or(0)
+    }
 }
 
 impl Deref for Monster {
@@ -98,65 +253,61 @@ pub fn move_to<R>(
 where
     R: Rng + RngCore,
 {
+    // confused creatures stumble around half the time
+    let stumble = level.monsters[idx].effects.has(EffectKind::Confusion)
+        && Uniform::new(0, 2).sample(rng) == 0;
+    let dpos = if stumble {
+        *DIRS.choose(rng).unwrap()
+    } else {
+        dpos
+    };
     let pos = level.monsters[idx].get_pos() + dpos;
+    // walking into a wall by accident still wastes the turn
+    if stumble && !level.passable(pos) {
+        let from = level.monsters[idx].pos;
+        if idx == 0 || level.tiles.is_in_fov(from.0 as usize, from.1 as usize) {
+            let noun = level.monsters[idx].info.noun();
+            log.push_back(fill(
+                "{subject} {stumble|stumbles} into the wall",
+                noun,
+                None,
+            ));
+        }
+        return true;
+    }
     if let Ok((ux, uy)) = pos.try_into() {
         if ux < level.width && uy < level.height {
             let tile = level.tiles.get(ux, uy);
             if tile.walkable {
-                let minfo = level.monsters[idx].info.clone();
-                for (i, mon) in level.monsters.iter_mut().enumerate() {
-                    if i != idx && mon.pos == pos && mon.hp > 0 {
-                        let attack = minfo.attacks.choose(rng).unwrap();
-                        let damage = attack.dam.roll(rng);
-                        let flavor = attack
-                            .text
-                            .as_ref()
-                            .unwrap_or_else(|| &info.damage[&attack.class].attacks);
-                        if idx == 0 {
-                            let (pre, post) = &flavor.choose(rng).unwrap().player;
-                            log.push_back("you".to_owned() + pre + "the " + &mon.info.name + post);
-                        } else if i == 0 {
-                            let (pre, post) = &flavor.choose(rng).unwrap().monster_p;
-                            log.push_back("the ".to_owned() + &minfo.name + pre + "you" + post);
+                match level.monster_at(pos) {
+                    Some(target) if target != idx => {
+                        let mon = &level.monsters[idx];
+                        if mon.hostile(&level.monsters[target]) {
+                            let attack = melee_attack(mon, rng);
+                            hit(idx, target, &attack, level, info, log, rng);
+                            true
+                        } else if target == 0 {
+                            // nobody pushes the player around
+                            false
                         } else {
-                            let (pre, post) = &flavor.choose(rng).unwrap().monster_p;
-                            log.push_back(
-                                "the ".to_owned()
-                                    + &minfo.name
-                                    + pre
-                                    + "the "
-                                    + &mon.info.name
-                                    + post,
-                            );
-                        }
-                        mon.hp -= damage;
-                        if mon.hp <= 0 {
-                            if i == 0 {
-                                log.push_back(
-                                    info.damage[&attack.class]
-                                        .deaths
-                                        .choose(rng)
-                                        .unwrap()
-                                        .player
-                                        .clone(),
-                                );
-                            } else {
-                                log.push_back(
-                                    "the".to_owned()
-                                        + &mon.info.name
-                                        + &info.damage[&attack.class]
-                                            .deaths
-                                            .choose(rng)
-                                            .unwrap()
-                                            .monster,
-                                );
+                            if idx == 0 {
+                                log.push_back(fill(
+                                    "{subject} swap places with {object}",
+                                    mon.info.noun(),
+                                    Some(level.monsters[target].info.noun()),
+                                ));
                             }
+                            let from = mon.pos;
+                            level.monsters[target].set_pos(from);
+                            level.monsters[idx].set_pos(pos);
+                            true
                         }
-                        return true;
+                    }
+                    _ => {
+                        level.monsters[idx].set_pos(pos);
+                        true
                     }
                 }
-                level.monsters[idx].set_pos(pos);
-                true
             } else if let Some(oname) = &tile.open {
                 let otile = info.map.tiles[Borrow::<String>::borrow(oname)].clone();
                 level.tiles.set(ux, uy, otile);
@@ -171,3 +322,176 @@ where
         false
     }
 }
+
+/// The wielded weapon's attack, or else one of the monster's own. Ranged attacks, wielded or
+/// not, are only used up close when there's nothing else.
+fn melee_attack<R: RngCore>(mon: &Monster, rng: &mut R) -> Attack {
+    if let Some(weapon) = mon
+        .equipment
+        .weapon
+        .as_ref()
+        .and_then(|w| w.weapon.as_ref())
+        .filter(|a| a.range.is_none())
+    {
+        return weapon.clone();
+    }
+    let melee: Vec<&Attack> = mon.attacks.iter().filter(|a| a.range.is_none()).collect();
+    match melee.choose(rng) {
+        Some(&a) => a.clone(),
+        None => mon.attacks.choose(rng).unwrap().clone(),
+    }
+}
+
+/// Shoots `attack` from the monster at `idx` towards `to`, hitting the first creature in the way.
+pub fn fire<R>(
+    idx: usize,
+    to: Point,
+    attack: &Attack,
+    level: &mut Level,
+    info: &GameInfo,
+    log: &mut VecDeque<String>,
+    rng: &mut R,
+) where
+    R: Rng + RngCore,
+{
+    let from = level.monsters[idx].pos;
+    let path = level.projectile_path(from, to, attack.range.unwrap_or(1));
+    match path.last().and_then(|&p| level.monster_at(p)) {
+        Some(target) => hit(idx, target, attack, level, info, log, rng),
+        None => {
+            if idx == 0 || level.tiles.is_in_fov(from.0 as usize, from.1 as usize) {
+                let noun = level.monsters[idx].info.noun();
+                log.push_back(fill("{subject} {hit|hits} nothing", noun, None));
+            }
+        }
+    }
+}
+
+/// Resolves one attack by the monster at `idx` on the monster at `target`, logging what happens.
+pub fn hit<R>(
+    idx: usize,
+    target: usize,
+    attack: &Attack,
+    level: &mut Level,
+    info: &GameInfo,
+    log: &mut VecDeque<String>,
+    rng: &mut R,
+) where
+    R: Rng + RngCore,
+{
+    // fights the player can't see happen quietly
+    let pos = level.monsters[target].pos;
+    let mut unseen = VecDeque::new();
+    let log = if idx == 0 || target == 0 || level.tiles.is_in_fov(pos.0 as usize, pos.1 as usize) {
+        log
+    } else {
+        &mut unseen
+    };
+    let minfo = level.monsters[idx].info.clone();
+    let (to_hit, bonus) = (
+        level.monsters[idx].to_hit(),
+        level.monsters[idx].damage_bonus(),
+    );
+    let mon = &mut level.monsters[target];
+    let class = &info.damage[&attack.class];
+    let mult = mon.damage_taken.get(&attack.class).copied().unwrap_or(1.0);
+    let (attacker, defender) = (minfo.noun(), mon.info.noun());
+    let hit = roll_to_hit(to_hit + attack.to_hit, mon.defense, rng);
+    if hit == Hit::Miss {
+        let template = class.misses.choose(rng).unwrap().template(idx, target);
+        log.push_back(fill(template, attacker, Some(defender)));
+        return;
+    }
+    let mut roll = attack.dam.roll(rng) + bonus;
+    if hit == Hit::Crit {
+        roll += attack.dam.roll(rng);
+    }
+    let rolled = scale_damage(roll, mult);
+    let absorbed = Uniform::new_inclusive(0, mon.armor().max(0)).sample(rng);
+    let damage = (rolled - absorbed).max(0);
+    let flavor = attack.text.as_ref().unwrap_or_else(|| &class.attacks);
+    let template = flavor.choose(rng).unwrap().template(idx, target);
+    log.push_back(fill(template, attacker, Some(defender)));
+    if hit == Hit::Crit {
+        log.push_back(fill(&class.critical, attacker, Some(defender)));
+    }
+    if let Some(template) = class.taken(mult) {
+        log.push_back(fill(template, defender, Some(attacker)));
+    }
+    if damage == 0 && mult > 0.0 {
+        log.push_back(fill(
+            if target == 0 {
+                "your armor absorbs the blow"
+            } else {
+                "{subject} {shrug|shrugs} it off"
+            },
+            defender,
+            None,
+        ));
+    }
+    mon.hp -= damage;
+    if damage > 0 && mon.hp > 0 {
+        for effect in &attack.effects {
+            if Uniform::new(0.0, 1.0).sample(rng) < effect.chance && mon.effects.add(effect) {
+                log.push_back(fill(effect.kind.text().0, defender, None));
+            }
+        }
+    }
+    if mon.hp <= 0 {
+        mon.killed_by = Some(attacker.a());
+        let death = class.deaths.choose(rng).unwrap();
+        let template = if target == 0 {
+            &death.player
+        } else {
+            &death.monster
+        };
+        log.push_back(fill(template, defender, Some(attacker)));
+        let xp = mon.info.xp;
+        let killer = &mut level.monsters[idx];
+        killer.kills += 1;
+        let gained = killer.gain_xp(xp, &info.settings.player);
+        if idx == 0 && gained > 0 {
+            log.push_back(format!("welcome to level {}!", killer.exp_level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: &str = "
+        name = 'test'
+        weight = 1
+        ch = 't'
+        fg = [255, 255, 255, 255]
+        health = 1
+        attacks = []
+    ";
+
+    fn monster(extra: &str) -> Monster {
+        let info: MonsterInfo = toml::from_str(&format!("{}{}", TEST, extra)).unwrap();
+        Monster::new(Rc::new(info), Point(0, 0))
+    }
+
+    #[test]
+    fn friends_fight_everyone_else() {
+        let ally = monster("friendly = true");
+        let zoomer = monster("faction = 'zoomers'");
+        let loner = monster("");
+        assert!(ally.hostile(&zoomer) && zoomer.hostile(&ally));
+        assert!(ally.hostile(&loner));
+        assert!(!ally.hostile(&monster("friendly = true")));
+    }
+
+    #[test]
+    fn factions_fight_each_other() {
+        let zoomer = monster("faction = 'zoomers'");
+        let boomer = monster("faction = 'boomers'");
+        let loner = monster("");
+        assert!(zoomer.hostile(&boomer));
+        assert!(!zoomer.hostile(&monster("faction = 'zoomers'")));
+        assert!(!zoomer.hostile(&loner) && !loner.hostile(&zoomer));
+        assert!(!loner.hostile(&monster("")));
+    }
+}
@@ -5,6 +5,34 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Point(pub i32, pub i32);
 
+impl Point {
+    /// Squared euclidean length
+    pub fn len2(self) -> i32 {
+        self.0 * self.0 + self.1 * self.1
+    }
+    /// Points on the line from `self` to `to` using Bresenham's algorithm, including both ends.
+    pub fn line_to(self, to: Point) -> Vec<Point> {
+        let (dx, dy) = ((to.0 - self.0).abs(), -(to.1 - self.1).abs());
+        let (sx, sy) = ((to.0 - self.0).signum(), (to.1 - self.1).signum());
+        let mut err = dx + dy;
+        let mut p = self;
+        let mut line = vec![p];
+        while p != to {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                p.0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                p.1 += sy;
+            }
+            line.push(p);
+        }
+        line
+    }
+}
+
 impl TryFrom<Point> for (usize, usize) {
     type Error = TryFromIntError;
 
@@ -66,3 +94,41 @@ impl DivAssign<i32> for Point {
         self.1 /= other;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_single() {
+        assert_eq!(Point(3, 4).line_to(Point(3, 4)), vec![Point(3, 4)]);
+    }
+    #[test]
+    fn line_straight() {
+        assert_eq!(
+            Point(0, 0).line_to(Point(0, -3)),
+            vec![Point(0, 0), Point(0, -1), Point(0, -2), Point(0, -3)]
+        );
+    }
+    #[test]
+    fn line_diagonal() {
+        assert_eq!(
+            Point(2, 2).line_to(Point(0, 0)),
+            vec![Point(2, 2), Point(1, 1), Point(0, 0)]
+        );
+    }
+    #[test]
+    fn line_shallow() {
+        assert_eq!(
+            Point(0, 0).line_to(Point(5, 2)),
+            vec![
+                Point(0, 0),
+                Point(1, 0),
+                Point(2, 1),
+                Point(3, 1),
+                Point(4, 2),
+                Point(5, 2)
+            ]
+        );
+    }
+}
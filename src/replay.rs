@@ -0,0 +1,73 @@
+use std::fs;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::screen::Key;
+
+/// Where recordings of the current run are written
+pub const REPLAY_PATH: &str = "replay.toml";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedKey {
+    pub key: String,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+impl RecordedKey {
+    pub fn as_key(&self) -> Key {
+        Key {
+            key: &self.key,
+            ctrl: self.ctrl,
+            alt: self.alt,
+            shift: self.shift,
+        }
+    }
+}
+
+/// The input handed to the screens during one update
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Frame {
+    pub tick: u64,
+    pub pressed: Vec<RecordedKey>,
+    /// held keys that fired this update
+    pub held: Vec<String>,
+}
+impl Frame {
+    pub fn is_empty(&self) -> bool {
+        self.pressed.is_empty() && self.held.is_empty()
+    }
+}
+
+/// Everything needed to play a run back exactly: the seed and every bit of input. Only runs
+/// started with "Play!" can be reproduced, since loading a save pulls in state from outside.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    // stored signed since toml can't hold a full u64
+    seed: i64,
+    /// whether the main menu had a "Continue" option, which shifts every other option down
+    pub has_continue: bool,
+    pub frames: Vec<Frame>,
+}
+impl Recording {
+    pub fn new(seed: u64, has_continue: bool) -> Recording {
+        Recording {
+            seed: seed as i64,
+            has_continue,
+            frames: Vec::new(),
+        }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed as u64
+    }
+
+    pub fn read(path: &str) -> Result<Recording, String> {
+        let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str(&data).map_err(|e| e.to_string())
+    }
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        let value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        let data = toml::to_string(&value).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| e.to_string())
+    }
+}
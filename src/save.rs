@@ -0,0 +1,374 @@
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use rand::{Rng, SeedableRng};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::ai::Awareness;
+use crate::effect::Effect;
+use crate::game::{Game, Levels};
+use crate::item::{Equipment, Inventory, Item, ItemInfo};
+use crate::map::Level;
+use crate::monster::Monster;
+use crate::point::Point;
+use crate::util::Grid;
+
+// Everything is stored by the key it has in the static toml files, so saves stay small and
+// loading hands out the same shared `Rc`s a freshly generated game would have.
+
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    level: i32,
+    turn: u64,
+    // seeds the rngs are reset to on save, toml can't hold a full u64
+    map_seed: i64,
+    play_seed: i64,
+    messages: Vec<String>,
+    floors: Vec<SaveLevel>,
+    basement: Vec<SaveLevel>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveLevel {
+    width: usize,
+    height: usize,
+    /// tile keys, indexed into by `tiles`
+    palette: Vec<String>,
+    tiles: Vec<usize>,
+    /// remembered characters, offset by one so 0 can mean never seen
+    seen: Vec<u32>,
+    monsters: Vec<SaveMonster>,
+    items: Vec<SaveItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveMonster {
+    kind: String,
+    pos: (i32, i32),
+    hp: i32,
+    // missing from saves made before experience levels
+    max_hp: Option<i32>,
+    #[serde(default)]
+    xp: u32,
+    #[serde(default = "one")]
+    exp_level: u32,
+    energy: i32,
+    awareness: String,
+    suspicion: u32,
+    target: Option<(i32, i32)>,
+    inventory: Vec<SaveItem>,
+    weapon: Option<SaveItem>,
+    armor: Option<SaveItem>,
+    #[serde(default)]
+    effects: Vec<Effect>,
+    #[serde(default)]
+    kills: u32,
+    killed_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveItem {
+    kind: String,
+    count: u32,
+    /// only for items lying on the floor
+    pos: Option<(i32, i32)>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Key of a shared value in one of the info maps
+fn key_of<T>(map: &HashMap<String, Rc<T>>, val: &Rc<T>) -> Result<String, String> {
+    map.iter()
+        .find(|(_, v)| Rc::ptr_eq(v, val))
+        .map(|(k, _)| k.clone())
+        .ok_or_else(|| "value missing from game info".to_owned())
+}
+fn lookup<T>(map: &HashMap<String, Rc<T>>, key: &str) -> Result<Rc<T>, String> {
+    map.get(key)
+        .cloned()
+        .ok_or_else(|| format!("unknown key in save: {}", key))
+}
+
+impl Game {
+    /// Reseeds both rngs from themselves, returning the new seeds. Saving does this, so anything
+    /// that skips saving has to do it too to roll the same numbers.
+    pub fn reseed(&mut self) -> (i64, i64) {
+        let map_seed = self.map_rng.gen::<i64>();
+        let play_seed = self.play_rng.gen::<i64>();
+        self.map_rng = SeedableRng::seed_from_u64(map_seed as u64);
+        self.play_rng = SeedableRng::seed_from_u64(play_seed as u64);
+        (map_seed, play_seed)
+    }
+
+    /// Serializes the whole game. Both rngs are reseeded so that the game carries on exactly the
+    /// same whether it keeps running or gets loaded back from this save.
+    pub fn save(&mut self) -> Result<String, String> {
+        let (map_seed, play_seed) = self.reseed();
+
+        let save = SaveGame {
+            level: self.levels.cur_idx(),
+            turn: self.turn,
+            map_seed,
+            play_seed,
+            messages: self.messages.iter().cloned().collect(),
+            floors: self
+                .levels
+                .floors()
+                .iter()
+                .map(|l| self.save_level(l))
+                .collect::<Result<_, _>>()?,
+            basement: self
+                .levels
+                .basement()
+                .iter()
+                .map(|l| self.save_level(l))
+                .collect::<Result<_, _>>()?,
+        };
+        let value = toml::Value::try_from(&save).map_err(|e| e.to_string())?;
+        toml::to_string(&value).map_err(|e| e.to_string())
+    }
+
+    /// Replaces the current game with one from `save`.
+    pub fn load(&mut self, save: &str) -> Result<(), String> {
+        let save: SaveGame = toml::from_str(save).map_err(|e| e.to_string())?;
+        let mut floors = Vec::with_capacity(save.floors.len());
+        for l in &save.floors {
+            floors.push(self.load_level(l)?);
+        }
+        let mut basement = Vec::with_capacity(save.basement.len());
+        for l in &save.basement {
+            basement.push(self.load_level(l)?);
+        }
+        self.levels = Levels::from_parts(save.level, floors, basement)?;
+        self.turn = save.turn;
+        self.messages = save.messages.into_iter().collect::<VecDeque<_>>();
+        self.map_rng = SeedableRng::seed_from_u64(save.map_seed as u64);
+        self.play_rng = SeedableRng::seed_from_u64(save.play_seed as u64);
+        self.from_save = true;
+        self.update_fov();
+        Ok(())
+    }
+
+    /// Saves the game to disk, or local storage on the web, logging a message if it fails.
+    /// A dead player's game is never saved.
+    pub fn autosave(&mut self) {
+        if !self.use_saves || self.player_dead() {
+            // reseed just like saving would, so replays and games without saves stay in step
+            self.reseed();
+            return;
+        }
+        if let Err(e) = self.save().and_then(|s| write(&s)) {
+            self.messages.push_back(format!("could not save: {}", e));
+        }
+    }
+
+    fn save_level(&self, level: &Level) -> Result<SaveLevel, String> {
+        let mut palette: Vec<String> = self.info.map.tiles.keys().cloned().collect();
+        palette.sort();
+        let mut tiles = Vec::with_capacity(level.width * level.height);
+        for y in 0..level.height {
+            for x in 0..level.width {
+                let tile = level.tiles.get_rc(x, y);
+                let idx = palette
+                    .iter()
+                    .position(|k| Rc::ptr_eq(&self.info.map.tiles[k], &tile))
+                    .ok_or_else(|| "tile missing from map info".to_owned())?;
+                tiles.push(idx);
+            }
+        }
+        Ok(SaveLevel {
+            width: level.width,
+            height: level.height,
+            palette,
+            tiles,
+            seen: level
+                .seen
+                .elems()
+                .iter()
+                .map(|s| s.map_or(0, |ch| ch as u32 + 1))
+                .collect(),
+            monsters: level
+                .monsters
+                .iter()
+                .map(|m| self.save_monster(m))
+                .collect::<Result<_, _>>()?,
+            items: level
+                .items
+                .iter()
+                .map(|(p, i)| self.save_item(i, Some(*p)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+    fn load_level(&self, save: &SaveLevel) -> Result<Level, String> {
+        let palette = save
+            .palette
+            .iter()
+            .map(|k| lookup(&self.info.map.tiles, k))
+            .collect::<Result<Vec<_>, _>>()?;
+        if save.tiles.len() != save.width * save.height || save.seen.len() != save.tiles.len() {
+            return Err("level has the wrong number of tiles".to_owned());
+        }
+        let mut level = Level::new(save.width, save.height, self.info.map.tiles["wall"].clone());
+        for (i, &t) in save.tiles.iter().enumerate() {
+            let tile = palette
+                .get(t)
+                .ok_or_else(|| "tile not in palette".to_owned())?;
+            level
+                .tiles
+                .set(i % save.width, i / save.width, tile.clone());
+        }
+        level.seen = Grid::from_vec(
+            save.seen
+                .iter()
+                .map(|&s| if s == 0 { None } else { Some((s - 1) as u16) })
+                .collect(),
+            save.width,
+        );
+        for m in &save.monsters {
+            level.monsters.push(self.load_monster(m)?);
+        }
+        for i in &save.items {
+            let (x, y) = i
+                .pos
+                .ok_or_else(|| "item on floor has no position".to_owned())?;
+            level.items.push((Point(x, y), self.load_item(i)?));
+        }
+        Ok(level)
+    }
+
+    fn save_monster(&self, mon: &Monster) -> Result<SaveMonster, String> {
+        let (awareness, suspicion) = match mon.awareness {
+            Awareness::Unaware => ("unaware", 0),
+            Awareness::Suspicious(n) => ("suspicious", n),
+            Awareness::Hunting => ("hunting", 0),
+        };
+        Ok(SaveMonster {
+            kind: if Rc::ptr_eq(&mon.info, &self.player_info) {
+                "player".to_owned()
+            } else {
+                key_of(&self.info.monster, &mon.info)?
+            },
+            pos: (mon.pos.0, mon.pos.1),
+            hp: mon.hp,
+            max_hp: Some(mon.max_hp),
+            xp: mon.xp,
+            exp_level: mon.exp_level,
+            energy: mon.energy,
+            awareness: awareness.to_owned(),
+            suspicion,
+            target: mon.target.map(|Point(x, y)| (x, y)),
+            inventory: mon
+                .inventory
+                .items
+                .iter()
+                .map(|i| self.save_item(i, None))
+                .collect::<Result<_, _>>()?,
+            weapon: match &mon.equipment.weapon {
+                Some(i) => Some(self.save_item(i, None)?),
+                None => None,
+            },
+            armor: match &mon.equipment.armor {
+                Some(i) => Some(self.save_item(i, None)?),
+                None => None,
+            },
+            effects: mon.effects.active.clone(),
+            kills: mon.kills,
+            killed_by: mon.killed_by.clone(),
+        })
+    }
+    fn load_monster(&self, save: &SaveMonster) -> Result<Monster, String> {
+        let info = if save.kind == "player" {
+            self.player_info.clone()
+        } else {
+            lookup(&self.info.monster, &save.kind)?
+        };
+        let mut mon = Monster::new(info, Point(save.pos.0, save.pos.1));
+        mon.hp = save.hp;
+        mon.max_hp = save.max_hp.unwrap_or(mon.max_hp);
+        mon.xp = save.xp;
+        mon.exp_level = save.exp_level;
+        mon.energy = save.energy;
+        mon.awareness = match save.awareness.as_str() {
+            "unaware" => Awareness::Unaware,
+            "suspicious" => Awareness::Suspicious(save.suspicion),
+            "hunting" => Awareness::Hunting,
+            a => return Err(format!("unknown awareness: {}", a)),
+        };
+        mon.target = save.target.map(|(x, y)| Point(x, y));
+        mon.inventory = Inventory {
+            items: save
+                .inventory
+                .iter()
+                .map(|i| self.load_item(i))
+                .collect::<Result<_, _>>()?,
+        };
+        mon.equipment = Equipment {
+            weapon: match &save.weapon {
+                Some(i) => Some(self.load_item(i)?),
+                None => None,
+            },
+            armor: match &save.armor {
+                Some(i) => Some(self.load_item(i)?),
+                None => None,
+            },
+        };
+        mon.effects.active = save.effects.clone();
+        mon.kills = save.kills;
+        mon.killed_by = save.killed_by.clone();
+        Ok(mon)
+    }
+
+    fn save_item(&self, item: &Item, pos: Option<Point>) -> Result<SaveItem, String> {
+        Ok(SaveItem {
+            kind: key_of(&self.info.item, &item.info)?,
+            count: item.count,
+            pos: pos.map(|Point(x, y)| (x, y)),
+        })
+    }
+    fn load_item(&self, save: &SaveItem) -> Result<Item, String> {
+        let info: Rc<ItemInfo> = lookup(&self.info.item, &save.kind)?;
+        Ok(Item {
+            info,
+            count: save.count,
+        })
+    }
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(test)))]
+const SAVE_PATH: &str = "save.toml";
+// keeps tests that turn saving on away from the real save
+#[cfg(all(not(target_arch = "wasm32"), test))]
+const SAVE_PATH: &str = "target/test_save.toml";
+#[cfg(target_arch = "wasm32")]
+const SAVE_KEY: &str = "wheatley_save";
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(data: &str) -> Result<(), String> {
+    std::fs::write(SAVE_PATH, data).map_err(|e| e.to_string())
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read() -> Option<String> {
+    std::fs::read_to_string(SAVE_PATH).ok()
+}
+#[cfg(not(target_arch = "wasm32"))]
+pub fn delete() {
+    let _ = std::fs::remove_file(SAVE_PATH);
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(data: &str) -> Result<(), String> {
+    stdweb::web::window()
+        .local_storage()
+        .insert(SAVE_KEY, data)
+        .map_err(|_| "local storage is full".to_owned())
+}
+#[cfg(target_arch = "wasm32")]
+pub fn read() -> Option<String> {
+    stdweb::web::window().local_storage().get(SAVE_KEY)
+}
+#[cfg(target_arch = "wasm32")]
+pub fn delete() {
+    stdweb::web::window().local_storage().remove(SAVE_KEY);
+}
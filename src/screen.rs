@@ -5,15 +5,28 @@ use doryen_rs::{Console, DoryenApi, Engine, UpdateEvent};
 
 use crate::colors::*;
 use crate::game::Game;
+use crate::replay::{Frame, RecordedKey, Recording, REPLAY_PATH};
 
+/// How many updates with input go by between writes of the recording
+const RECORDING_FLUSH: usize = 50;
+
+pub mod character;
 pub mod game;
+pub mod gameover;
+pub mod inventory;
 pub mod menu;
+pub mod target;
 pub mod textbox;
 
 pub struct WheatleyEngine {
     game: Game,
     screens: Vec<Rc<dyn Screen>>,
     held_keys: HashMap<String, u32>,
+    tick: u64,
+    /// input of this run so far, if it's being recorded
+    recording: Option<Recording>,
+    /// a run being played back, and the next frame to play
+    replay: Option<(Recording, usize)>,
 }
 
 impl WheatleyEngine {
@@ -24,97 +37,163 @@ impl WheatleyEngine {
             game,
             screens: vec![menu],
             held_keys: HashMap::new(),
+            tick: 0,
+            recording: None,
+            replay: None,
         }
     }
-}
-impl Engine for WheatleyEngine {
-    fn init(&mut self, api: &mut dyn DoryenApi) {
-        let con = api.con();
-        con.register_color("gray", GREY);
+    /// Records all input into `recording`, writing it to `REPLAY_PATH` every `RECORDING_FLUSH`
+    /// updates with any and on the way out. Recording stops if a save gets loaded.
+    pub fn record(mut self, recording: Recording) -> Self {
+        self.recording = Some(recording);
+        self
+    }
+    /// Plays back `recording` instead of taking input, until it runs out.
+    pub fn replay(mut self, recording: Recording) -> Self {
+        self.game.use_saves = false;
+        self.replay = Some((recording, 0));
+        self
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+    pub fn game_mut(&mut self) -> &mut Game {
+        &mut self.game
+    }
+    pub fn screens(&self) -> &[Rc<dyn Screen>] {
+        &self.screens
     }
-    fn update(&mut self, api: &mut dyn DoryenApi) -> Option<UpdateEvent> {
-        let game = &mut self.game;
-        let input = api.input();
 
+    /// Hands one update's worth of input to the screens. Returns whether the screen changed.
+    pub fn step(&mut self, frame: &Frame) -> bool {
         let mut clear = false;
+        for key in &frame.pressed {
+            let screen = match self.screens.last() {
+                Some(s) => s.clone(),
+                None => break,
+            };
+            let action = screen.handle(&mut self.game, key.as_key());
+            clear |= self.apply(action);
+        }
+
+        // the key that switched screens shouldn't also act on the new one
+        if !clear {
+            if let Some(screen) = self.screens.last().cloned() {
+                let held = &frame.held;
+                let action = screen.handle_held(
+                    &mut self.game,
+                    Box::new(move |k| held.iter().any(|h| h == k)),
+                );
+                clear |= self.apply(action);
+            }
+        }
+        clear
+    }
+
+    /// Carries out what a screen asked for. Returns whether the screen changed.
+    fn apply(&mut self, action: Action) -> bool {
+        let game = &mut self.game;
+        match action {
+            Action::Keep => false,
+            Action::Push(s) => {
+                s.enter(game);
+                self.screens.push(s);
+                true
+            }
+            Action::Replace(s) => {
+                self.screens.pop().unwrap().exit(game);
+                s.enter(game);
+                self.screens.push(s);
+                true
+            }
+            Action::Pop => {
+                if !cfg!(target_arch = "wasm32") || self.screens.len() != 1 {
+                    self.screens.pop().unwrap().exit(game);
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Gathers this update's input from the keyboard
+    fn read_input(&mut self, api: &mut dyn DoryenApi) -> Frame {
+        let input = api.input();
+        let mut pressed = Vec::new();
         for key in input.keys_pressed() {
             if !self.held_keys.contains_key(key) {
                 self.held_keys.insert(key.to_owned(), 0);
             }
             match key {
                 "ControlLeft" | "AltLeft" | "ShiftLeft" | "ShiftRight" => {}
-                _ => {
-                    let action = self.screens.last_mut().unwrap().handle(
-                        game,
-                        Key {
-                            key,
-                            ctrl: input.key("ControlLeft"),
-                            alt: input.key("AltLeft"),
-                            shift: input.key("ShiftLeft") | input.key("ShiftRight"),
-                        },
-                    );
-                    match action {
-                        Action::Keep => {}
-                        Action::Push(s) => {
-                            s.enter(game);
-                            self.screens.push(s.clone());
-                            clear = true;
-                        }
-                        Action::Replace(s) => {
-                            self.screens.pop().unwrap().exit(game);
-                            s.enter(game);
-                            self.screens.push(s.clone());
-                            clear = true;
-                        }
-                        Action::Pop => {
-                            if !cfg!(target_arch = "wasm32") || self.screens.len() != 1 {
-                                self.screens.pop().unwrap().exit(game);
-                                clear = true;
-                            }
-                        }
-                    }
-                }
+                _ => pressed.push(RecordedKey {
+                    key: key.to_owned(),
+                    ctrl: input.key("ControlLeft"),
+                    alt: input.key("AltLeft"),
+                    shift: input.key("ShiftLeft") | input.key("ShiftRight"),
+                }),
             }
         }
-        if clear {
-            api.con().clear(
-                Some((255, 255, 255, 255)),
-                Some((0, 0, 0, 255)),
-                Some(' ' as u16),
-            );
-        }
-
-        for key in api.input().keys_released() {
+        for key in input.keys_released() {
             self.held_keys.remove(key);
         }
 
-        let keys = &self.held_keys;
-        let key_delay = game.info.settings.interface.key_delay;
-        self.screens.last_mut().map(|s| {
-            s.handle_held(
-                game,
-                Box::new(|k| {
-                    if let Some(i) = keys.get(k) {
-                        *i == 0 || *i > key_delay
-                    } else {
-                        false
-                    }
-                }),
-            )
-        });
-
+        let key_delay = self.game.info.settings.interface.key_delay;
+        let mut held: Vec<String> = self
+            .held_keys
+            .iter()
+            .filter(|(_, &i)| i == 0 || i > key_delay)
+            .map(|(k, _)| k.clone())
+            .collect();
+        held.sort();
         for i in self.held_keys.values_mut() {
             *i += 1;
         }
 
-        if self.screens.is_empty() {
-            Some(UpdateEvent::Exit)
-        } else {
-            None
+        Frame {
+            tick: self.tick,
+            pressed,
+            held,
         }
     }
-    fn render(&mut self, api: &mut dyn DoryenApi) {
-        let con = api.con();
+
+    /// Gathers this update's input from the replay, handing control back once it runs out
+    fn replay_input(&mut self) -> Frame {
+        let mut frame = Frame {
+            tick: self.tick,
+            ..Frame::default()
+        };
+        let mut finished = false;
+        if let Some((recording, next)) = &mut self.replay {
+            while *next < recording.frames.len() && recording.frames[*next].tick <= self.tick {
+                let f = &recording.frames[*next];
+                frame.pressed.extend(f.pressed.iter().cloned());
+                frame.held.extend(f.held.iter().cloned());
+                *next += 1;
+            }
+            finished = *next >= recording.frames.len();
+        }
+        if finished {
+            self.game.messages.push_back("replay finished".to_owned());
+            self.replay = None;
+        }
+        frame
+    }
+
+    fn write_recording(&mut self) {
+        if let Some(recording) = &self.recording {
+            if let Err(e) = recording.write(REPLAY_PATH) {
+                self.game
+                    .messages
+                    .push_back(format!("could not write replay: {}", e));
+            }
+        }
+    }
+
+    /// Draws every screen down to the topmost one that isn't transparent
+    pub fn render_to(&mut self, con: &mut Console) {
         let game = &mut self.game;
         con.clear(None, None, Some(' ' as u16));
 
@@ -129,6 +208,58 @@ impl Engine for WheatleyEngine {
         }
     }
 }
+impl Engine for WheatleyEngine {
+    fn init(&mut self, api: &mut dyn DoryenApi) {
+        let con = api.con();
+        con.register_color("gray", GREY);
+        con.register_color("red", RED);
+    }
+    fn update(&mut self, api: &mut dyn DoryenApi) -> Option<UpdateEvent> {
+        let frame = if self.replay.is_some() {
+            self.replay_input()
+        } else {
+            self.read_input(api)
+        };
+
+        if self.step(&frame) {
+            api.con().clear(
+                Some((255, 255, 255, 255)),
+                Some((0, 0, 0, 255)),
+                Some(' ' as u16),
+            );
+        }
+        // a loaded save can't be rebuilt from the seed, so there's nothing worth keeping
+        if self.game.from_save && self.recording.take().is_some() {
+            self.game
+                .messages
+                .push_back("runs continued from a save aren't recorded".to_owned());
+        }
+        if !frame.is_empty() {
+            let flush = match &mut self.recording {
+                Some(recording) => {
+                    recording.frames.push(frame);
+                    recording.frames.len() % RECORDING_FLUSH == 0
+                }
+                None => false,
+            };
+            // written every so often, so a crash loses little of it
+            if flush {
+                self.write_recording();
+            }
+        }
+        self.tick += 1;
+
+        if self.screens.is_empty() {
+            self.write_recording();
+            Some(UpdateEvent::Exit)
+        } else {
+            None
+        }
+    }
+    fn render(&mut self, api: &mut dyn DoryenApi) {
+        self.render_to(api.con());
+    }
+}
 
 #[derive(Clone)]
 pub enum Action {
@@ -138,13 +269,12 @@ pub enum Action {
     Replace(Rc<dyn Screen>),
 }
 
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Key<'a> {
-    key: &'a str,
-    ctrl: bool,
-    alt: bool,
-    shift: bool,
+    pub key: &'a str,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
 }
 
 pub trait Screen {
@@ -154,7 +284,9 @@ pub trait Screen {
     fn handle(&self, game: &mut Game, key: Key) -> Action {
         handle_default(game, key)
     }
-    fn handle_held<'a>(&self, _game: &mut Game, _held: Box<dyn Fn(&str) -> bool + 'a>) {}
+    fn handle_held<'a>(&self, _game: &mut Game, _held: Box<dyn Fn(&str) -> bool + 'a>) -> Action {
+        Action::Keep
+    }
     fn transparent(&self) -> bool {
         false
     }
@@ -0,0 +1,63 @@
+use doryen_rs::{Console, TextAlign};
+
+use super::Screen;
+use crate::game::Game;
+
+const WIDTH: u32 = 40;
+
+/// The player's level, experience and stats
+pub struct CharacterScreen;
+
+impl Screen for CharacterScreen {
+    fn render(&self, game: &mut Game, con: &mut Console) {
+        let player = &game.levels.cur().monsters[0];
+        let mut lines = vec![
+            format!("level: {}", player.exp_level),
+            format!(
+                "experience: {}/{}",
+                player.xp,
+                player.next_level_xp(&game.info.settings.player)
+            ),
+            format!("health: {}/{}", player.hp.max(0), player.max_hp),
+            format!("to-hit: {:+}", player.to_hit()),
+            format!("damage: {:+}", player.damage_bonus()),
+            format!("defense: {}", player.defense),
+            format!("armor: {}", player.armor()),
+            format!("speed: {}", player.speed()),
+            format!("kills: {}", player.kills),
+            format!("turns: {}", game.turn),
+        ];
+        if !player.effects.active.is_empty() {
+            lines.push(String::new());
+            for effect in &player.effects.active {
+                lines.push(format!("{} for {} turns", effect.kind.name(), effect.turns));
+            }
+        }
+
+        let height = lines.len() as u32;
+        let x = con.get_width().saturating_sub(WIDTH) as i32 / 2;
+        let y = con.get_height().saturating_sub(height) as i32 / 2;
+        con.rectangle(
+            x - 1,
+            y - 1,
+            WIDTH + 2,
+            height + 2,
+            None,
+            None,
+            Some(' ' as u16),
+        );
+        con.print_color(
+            con.get_width() as i32 / 2,
+            y - 1,
+            "Character",
+            TextAlign::Center,
+            None,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            con.print_color(x + 1, y + i as i32, line, TextAlign::Left, None);
+        }
+    }
+    fn transparent(&self) -> bool {
+        true
+    }
+}
@@ -1,40 +1,96 @@
 use std::borrow::Borrow;
 use std::cell::Cell;
 use std::convert::TryInto;
+use std::rc::Rc;
 
 use doryen_rs::{Console, TextAlign};
 
+use super::character::CharacterScreen;
+use super::gameover::GameOverScreen;
+use super::inventory::{InventoryMode, InventoryScreen};
+use super::target::TargetScreen;
 use super::{handle_default, Action, Key, Screen};
+use crate::ai::Awareness;
 use crate::colors::*;
 use crate::game::Game;
+use crate::item::Slot;
+use crate::map::Stairs;
 use crate::monster::move_to;
 use crate::point::Point;
+use crate::save;
+
+/// Keys for each direction: vi-keys, numpad and arrows
+pub const DIR_KEYS: [(Point, &[&str]); 8] = [
+    (Point(-1, -1), &["KeyY", "Numpad7"]),
+    (Point(0, -1), &["KeyK", "Numpad8", "ArrowUp"]),
+    (Point(1, -1), &["KeyU", "Numpad9"]),
+    (Point(-1, 0), &["KeyH", "Numpad4", "ArrowLeft"]),
+    (Point(1, 0), &["KeyL", "Numpad6", "ArrowRight"]),
+    (Point(-1, 1), &["KeyB", "Numpad1"]),
+    (Point(0, 1), &["KeyJ", "Numpad2", "ArrowDown"]),
+    (Point(1, 1), &["KeyN", "Numpad3"]),
+];
+
+/// Where the top left corner of the console is on the map, keeping the player in the middle
+pub fn map_offset(game: &Game, con: &Console) -> Point {
+    let (w, h) = (con.get_width() as i32, con.get_height() as i32);
+    game.levels.cur().monsters[0].pos - Point(w, h) / 2
+}
 
 pub struct GameScreen {
     entered: Cell<bool>,
+    resume: bool,
 }
 
 impl GameScreen {
     pub fn new() -> GameScreen {
         GameScreen {
             entered: Cell::new(false),
+            resume: false,
+        }
+    }
+    /// A game screen that loads the saved game when entered
+    pub fn resume() -> GameScreen {
+        GameScreen {
+            entered: Cell::new(false),
+            resume: true,
         }
     }
 }
 
 impl Screen for GameScreen {
     fn enter(&self, game: &mut Game) {
-        if !self.entered.get() {
+        if self.resume {
+            let data = if game.use_saves {
+                save::read().ok_or_else(|| "no saved game".to_owned())
+            } else {
+                Err("saves are disabled".to_owned())
+            };
+            match data.and_then(|d| game.load(&d)) {
+                Ok(()) => game.messages.push_back("Welcome back!".to_owned()),
+                Err(e) => game
+                    .messages
+                    .push_back(format!("could not load save: {}", e)),
+            }
+        }
+        // coming back to a game the player already died in starts over
+        if game.player_dead() {
+            game.reset();
+            game.messages.push_back("Welcome to Wheatley!".to_owned());
+        } else if !self.resume && !self.entered.get() {
             game.messages.push_back("Welcome to Wheatley!".to_owned());
-            self.entered.set(false);
         }
+        self.entered.set(true);
+    }
+    fn exit(&self, game: &mut Game) {
+        game.autosave();
     }
     fn render(&self, game: &mut Game, con: &mut Console) {
+        let offset = map_offset(game, con);
         let level = game.levels.cur_mut();
         let (w, h) = (con.get_width() as i32, con.get_height() as i32);
-        let offset = level.monsters[0].pos - Point(w, h) / 2;
         for x in 0..w {
-            for y in 0..h - 5 {
+            for y in 0..h - 6 {
                 let p = Point(x, y) + offset;
                 if 0 <= p.0 && p.0 < level.width as i32 && 0 <= p.1 && p.1 < level.height as i32 {
                     let (ux, uy) = p.try_into().unwrap();
@@ -49,6 +105,12 @@ impl Screen for GameScreen {
                 }
             }
         }
+        for (pos, item) in &level.items {
+            if level.tiles.is_in_fov(pos.0 as usize, pos.1 as usize) {
+                item.draw(*pos - offset, con);
+                level.seen[[pos.0 as usize, pos.1 as usize]] = Some(item.ch);
+            }
+        }
         for mon in &level.monsters {
             if mon.hp > 0
                 && level
@@ -57,9 +119,26 @@ impl Screen for GameScreen {
             {
                 let p = mon.pos - offset;
                 mon.draw(p, con);
+                match mon.awareness {
+                    _ if mon.friendly => {}
+                    Awareness::Hunting => con.back(p.0, p.1, DARKEST_RED),
+                    Awareness::Suspicious(_) => con.back(p.0, p.1, DARKEST_AMBER),
+                    Awareness::Unaware => {}
+                }
                 level.seen[[mon.pos.0 as usize, mon.pos.1 as usize]] = Some(mon.ch);
             }
         }
+        let player = &level.monsters[0];
+        let mut status = format!(
+            "HP {}/{}  Lv {}",
+            player.hp.max(0),
+            player.max_hp,
+            player.exp_level
+        );
+        for effect in &player.effects.active {
+            status += &format!("  {} ({})", effect.kind.name(), effect.turns);
+        }
+        con.print_color(0, h - 6, &status, TextAlign::Left, None);
         for (i, msg) in game.messages.iter().rev().take(5).enumerate() {
             // println!("{}: {}", con.get_height() as i32 - i as i32, msg);
             con.print_color(
@@ -72,6 +151,9 @@ impl Screen for GameScreen {
         }
     }
     fn handle(&self, game: &mut Game, key: Key) -> Action {
+        if game.player_dead() {
+            return Action::Keep;
+        }
         let pos = game.levels.cur().monsters[0].pos;
         let l = game.levels.cur_mut();
         match key {
@@ -89,30 +171,78 @@ impl Screen for GameScreen {
                     }
                 }
             }
+            Key {
+                key: "Comma",
+                shift: true,
+                ..
+            } => {
+                if game.take_stairs(Stairs::Up) {
+                    game.end_turn();
+                }
+            }
+            Key {
+                key: "Period",
+                shift: true,
+                ..
+            } => {
+                if game.take_stairs(Stairs::Down) {
+                    game.end_turn();
+                }
+            }
+            Key { key: "Period", .. }
+            | Key { key: "Numpad5", .. }
+            | Key { key: "Digit5", .. }
+            | Key { key: "KeyS", .. } => game.rest(),
+            Key { key: "KeyG", .. } | Key { key: "Comma", .. } => {
+                if game.pick_up() {
+                    game.end_turn();
+                }
+            }
+            Key { key: "KeyD", .. } => {
+                return Action::Push(Rc::new(InventoryScreen::new(InventoryMode::Drop)))
+            }
+            Key { key: "KeyI", .. } => {
+                return Action::Push(Rc::new(InventoryScreen::new(InventoryMode::View)))
+            }
+            Key {
+                key: "KeyW",
+                shift: false,
+                ..
+            } => return Action::Push(Rc::new(InventoryScreen::new(InventoryMode::Wield))),
+            Key {
+                key: "KeyW",
+                shift: true,
+                ..
+            } => return Action::Push(Rc::new(InventoryScreen::new(InventoryMode::Wear))),
+            Key { key: "KeyA", .. } => {
+                return Action::Push(Rc::new(InventoryScreen::new(InventoryMode::Use)))
+            }
+            Key { key: "KeyF", .. } => match game.ranged_attack() {
+                Some(attack) => return Action::Push(Rc::new(TargetScreen::new(game, attack))),
+                None => game
+                    .messages
+                    .push_back("you have nothing to shoot with".to_owned()),
+            },
+            Key { key: "KeyX", .. } => return Action::Push(Rc::new(CharacterScreen)),
+            Key { key: "KeyT", .. } => {
+                if game.unequip(Slot::Armor) {
+                    game.end_turn();
+                }
+            }
             _ => return handle_default(game, key),
         }
         Action::Keep
     }
-    fn handle_held<'a>(&self, game: &mut Game, held: Box<dyn Fn(&str) -> bool + 'a>) {
-        let dpos = if held("KeyY") || held("Numpad7") {
-            Point(-1, -1)
-        } else if held("KeyK") || held("Numpad8") || held("ArrowUp") {
-            Point(0, -1)
-        } else if held("KeyU") || held("Numpad9") {
-            Point(1, -1)
-        } else if held("KeyH") || held("Numpad4") || held("ArrowLeft") {
-            Point(-1, 0)
-        } else if held("KeyL") || held("Numpad6") || held("ArrowRight") {
-            Point(1, 0)
-        } else if held("KeyB") || held("Numpad1") {
-            Point(-1, 1)
-        } else if held("KeyJ") || held("Numpad2") || held("ArrowDown") {
-            Point(0, 1)
-        } else if held("KeyN") || held("Numpad3") {
-            Point(1, 1)
-        } else {
-            Point(0, 0)
-        };
+    fn handle_held<'a>(&self, game: &mut Game, held: Box<dyn Fn(&str) -> bool + 'a>) -> Action {
+        // checked here rather than after each action, since held keys are handled every update
+        // no matter what killed the player
+        if game.player_dead() {
+            return Action::Replace(Rc::new(GameOverScreen::new(game)));
+        }
+        let dpos = DIR_KEYS
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| held(k)))
+            .map_or(Point(0, 0), |(d, _)| *d);
         let level = game.levels.cur_mut();
         let tick = if dpos != Point(0, 0) {
             move_to(
@@ -127,13 +257,8 @@ impl Screen for GameScreen {
             false
         };
         if tick {
-            let player = &level.monsters[0];
-            let fov = game.info.settings.player.fov;
-            let pos = player.pos;
-            game.levels
-                .cur_mut()
-                .tiles
-                .compute_fov(pos.0 as usize, pos.1 as usize, fov);
+            game.end_turn();
         }
+        Action::Keep
     }
 }
@@ -0,0 +1,153 @@
+use std::convert::TryInto;
+
+use doryen_rs::{Console, TextAlign};
+
+use super::{Action, Key, Screen};
+use crate::game::Game;
+use crate::point::Point;
+use crate::save;
+
+/// Size of the map snapshot in the morgue file
+const SNAPSHOT_WIDTH: i32 = 60;
+const SNAPSHOT_HEIGHT: i32 = 20;
+/// How many of the last messages end up in the morgue file
+const MORGUE_MESSAGES: usize = 20;
+
+/// Shown once the player dies, writes the morgue file and goes back to the main menu
+pub struct GameOverScreen {
+    summary: String,
+    morgue: String,
+}
+
+impl GameOverScreen {
+    pub fn new(game: &Game) -> GameOverScreen {
+        let player = &game.levels.cur().monsters[0];
+        let summary = format!(
+            "You died on {} after {} turns, at level {}.\nKilled by {}.\nYou took down {} {}.",
+            depth_name(game.levels.cur_idx()),
+            game.turn,
+            player.exp_level,
+            player.killed_by.as_deref().unwrap_or("something"),
+            player.kills,
+            if player.kills == 1 {
+                "monster"
+            } else {
+                "monsters"
+            },
+        );
+
+        let mut morgue = String::from("Wheatley Simulator morgue file\n\n");
+        morgue += &summary;
+        morgue += "\n\n";
+        morgue += &snapshot(game);
+        morgue += "\nLast messages:\n";
+        let skip = game.messages.len().saturating_sub(MORGUE_MESSAGES);
+        for msg in game.messages.iter().skip(skip) {
+            morgue += msg;
+            morgue += "\n";
+        }
+        GameOverScreen { summary, morgue }
+    }
+}
+
+impl Screen for GameOverScreen {
+    fn enter(&self, game: &mut Game) {
+        if !game.use_saves {
+            return;
+        }
+        save::delete();
+        if let Err(e) = write_morgue(&self.morgue) {
+            game.messages
+                .push_back(format!("could not write morgue file: {}", e));
+        }
+    }
+    fn render(&self, _game: &mut Game, con: &mut Console) {
+        let x = con.get_width() as i32 / 2;
+        let mut y = con.get_height() as i32 / 2 - 4;
+        con.print_color(x, y, "%{red}GAME OVER", TextAlign::Center, None);
+        y += 2;
+        for line in self.summary.lines() {
+            con.print_color(x, y, line, TextAlign::Center, None);
+            y += 1;
+        }
+        con.print_color(
+            x,
+            y + 1,
+            "%{gray}Press Enter to return to the main menu",
+            TextAlign::Center,
+            None,
+        );
+    }
+    fn handle(&self, _game: &mut Game, key: Key) -> Action {
+        match key {
+            Key { key: "Enter", .. } | Key { key: "Escape", .. } => Action::Pop,
+            _ => Action::Keep,
+        }
+    }
+}
+
+fn depth_name(level: i32) -> String {
+    match level {
+        0 => "the ground floor".to_owned(),
+        n if n > 0 => format!("floor {}", n),
+        n => format!("basement level {}", -n),
+    }
+}
+
+/// What the player could see of the level around where they died
+fn snapshot(game: &Game) -> String {
+    let level = game.levels.cur();
+    let center = level.monsters[0].pos;
+    let offset = center - Point(SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT) / 2;
+    let mut out = String::new();
+    for y in 0..SNAPSHOT_HEIGHT {
+        let mut line = String::new();
+        for x in 0..SNAPSHOT_WIDTH {
+            let p = Point(x, y) + offset;
+            let up: Result<(usize, usize), _> = p.try_into();
+            let ch = match up {
+                Ok((ux, uy)) if ux < level.width && uy < level.height => {
+                    if p == center {
+                        Some('@' as u16)
+                    } else if level.tiles.is_in_fov(ux, uy) {
+                        level
+                            .monsters
+                            .iter()
+                            .find(|m| m.hp > 0 && m.pos == p)
+                            .map(|m| m.ch)
+                            .or_else(|| {
+                                level
+                                    .items
+                                    .iter()
+                                    .find(|(ip, _)| *ip == p)
+                                    .map(|(_, i)| i.ch)
+                            })
+                            .or_else(|| Some(level.tiles.get(ux, uy).ch))
+                    } else {
+                        level.seen[[ux, uy]]
+                    }
+                }
+                _ => None,
+            };
+            line.push(
+                ch.and_then(|c| std::char::from_u32(c as u32))
+                    .unwrap_or(' '),
+            );
+        }
+        out += line.trim_end();
+        out += "\n";
+    }
+    out
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_morgue(data: &str) -> Result<(), String> {
+    std::fs::write("morgue.txt", data).map_err(|e| e.to_string())
+}
+#[cfg(target_arch = "wasm32")]
+fn write_morgue(data: &str) -> Result<(), String> {
+    stdweb::web::window()
+        .local_storage()
+        .insert("wheatley_morgue", data)
+        .map_err(|_| "local storage is full".to_owned())
+}
@@ -0,0 +1,191 @@
+use std::cell::Cell;
+
+use doryen_rs::{Console, TextAlign};
+
+use super::{handle_default, Action, Key, Screen};
+use crate::colors::*;
+use crate::game::Game;
+use crate::item::Slot;
+
+const WIDTH: u32 = 50;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InventoryMode {
+    View,
+    Drop,
+    Wield,
+    Wear,
+    Use,
+}
+
+pub struct InventoryScreen {
+    mode: InventoryMode,
+    active: Cell<usize>,
+}
+impl InventoryScreen {
+    pub fn new(mode: InventoryMode) -> InventoryScreen {
+        InventoryScreen {
+            mode,
+            active: Cell::new(0),
+        }
+    }
+    fn select(&self, game: &mut Game, idx: usize) -> Action {
+        match self.mode {
+            InventoryMode::View => Action::Keep,
+            InventoryMode::Drop => {
+                game.drop_item(idx);
+                game.end_turn();
+                Action::Pop
+            }
+            InventoryMode::Use => {
+                if game.use_item(idx) {
+                    game.end_turn();
+                }
+                Action::Pop
+            }
+            InventoryMode::Wield | InventoryMode::Wear => {
+                let slot = if self.mode == InventoryMode::Wield {
+                    Slot::Weapon
+                } else {
+                    Slot::Armor
+                };
+                if game.equip(idx, slot) {
+                    game.end_turn();
+                }
+                Action::Pop
+            }
+        }
+    }
+}
+
+/// The inventory slot selected by a letter key, `KeyA` is slot 0
+pub fn letter_index(key: &str) -> Option<usize> {
+    let b = key.as_bytes();
+    if b.len() == 4 && key.starts_with("Key") && b[3].is_ascii_uppercase() {
+        Some((b[3] - b'A') as usize)
+    } else {
+        None
+    }
+}
+pub fn slot_letter(idx: usize) -> char {
+    (b'a' + idx as u8) as char
+}
+
+impl Screen for InventoryScreen {
+    fn render(&self, game: &mut Game, con: &mut Console) {
+        let player = &game.levels.cur().monsters[0];
+        let items = &player.inventory.items;
+        let height = items.len().max(1) as u32 + 5;
+        let x = con.get_width().saturating_sub(WIDTH) as i32 / 2;
+        let y = con.get_height().saturating_sub(height) as i32 / 2;
+        con.rectangle(
+            x - 1,
+            y - 1,
+            WIDTH + 2,
+            height + 2,
+            None,
+            None,
+            Some(' ' as u16),
+        );
+        let title = match self.mode {
+            InventoryMode::View => "Inventory",
+            InventoryMode::Drop => "Drop what?",
+            InventoryMode::Wield => "Wield what? (- for nothing)",
+            InventoryMode::Wear => "Wear what?",
+            InventoryMode::Use => "Use what?",
+        };
+        con.print_color(
+            con.get_width() as i32 / 2,
+            y - 1,
+            title,
+            TextAlign::Center,
+            None,
+        );
+        if items.is_empty() {
+            con.print_color(
+                x + 2,
+                y,
+                "you aren't carrying anything",
+                TextAlign::Left,
+                None,
+            );
+        }
+        for (i, item) in items.iter().enumerate() {
+            let line = if item.count > 1 {
+                format!("{} - {} (x{})", slot_letter(i), item.name, item.count)
+            } else {
+                format!("{} - {}", slot_letter(i), item.name)
+            };
+            con.print_color(x + 2, y + i as i32, &line, TextAlign::Left, None);
+            if i == self.active.get() {
+                con.cell(x, y + i as i32, Some('>' as u16), Some(GREY), None);
+            }
+        }
+        for (i, (label, slot)) in [("wielding", Slot::Weapon), ("wearing", Slot::Armor)]
+            .iter()
+            .enumerate()
+        {
+            let line = match player.equipment.get(*slot) {
+                Some(item) => format!("{}: {}", label, item.name),
+                None => format!("{}: nothing", label),
+            };
+            con.print_color(
+                x + 2,
+                y + height as i32 - 4 + i as i32,
+                &line,
+                TextAlign::Left,
+                None,
+            );
+        }
+        let weight = format!(
+            "weight: {:.1}/{:.1}",
+            player.inventory.weight(),
+            game.info.settings.player.max_weight
+        );
+        con.print_color(x + 2, y + height as i32 - 1, &weight, TextAlign::Left, None);
+    }
+    fn handle(&self, game: &mut Game, key: Key) -> Action {
+        let len = game.levels.cur().monsters[0].inventory.items.len();
+        if let Some(i) = letter_index(key.key) {
+            if i < len {
+                return self.select(game, i);
+            }
+        }
+        match key {
+            Key {
+                key: "ArrowDown", ..
+            } => {
+                if len > 0 {
+                    self.active.set((self.active.get() + 1) % len)
+                }
+            }
+            Key { key: "Minus", .. } if self.mode == InventoryMode::Wield => {
+                if game.unequip(Slot::Weapon) {
+                    game.end_turn();
+                }
+                return Action::Pop;
+            }
+            Key { key: "ArrowUp", .. } => {
+                let active = self.active.get();
+                if active == 0 {
+                    self.active.set(len.max(1) - 1);
+                } else {
+                    self.active.set(active - 1)
+                }
+            }
+            Key { key: "Enter", .. }
+            | Key {
+                key: "NumpadEnter", ..
+            } => {
+                if self.active.get() < len {
+                    return self.select(game, self.active.get());
+                }
+            }
+            _ => return handle_default(game, key),
+        }
+        Action::Keep
+    }
+    fn transparent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,106 @@
+use std::cell::Cell;
+
+use doryen_rs::{Console, TextAlign};
+
+use super::game::{map_offset, DIR_KEYS};
+use super::{handle_default, Action, Key, Screen};
+use crate::colors::*;
+use crate::game::Game;
+use crate::monster::Attack;
+use crate::point::Point;
+
+/// Picks where to shoot, drawn over the game screen
+pub struct TargetScreen {
+    range: usize,
+    /// visible hostile creatures in range, closest first
+    targets: Vec<Point>,
+    /// which of `targets` the cursor was last put on
+    next: Cell<usize>,
+    cursor: Cell<Point>,
+}
+
+impl TargetScreen {
+    pub fn new(game: &Game, attack: Attack) -> TargetScreen {
+        let range = attack.range.unwrap_or(1);
+        let level = game.levels.cur();
+        let ppos = level.monsters[0].pos;
+        let r = range as i32;
+        let mut targets: Vec<Point> = level.monsters[1..]
+            .iter()
+            .filter(|m| {
+                m.hp > 0
+                    && level.monsters[0].hostile(m)
+                    && level.tiles.is_in_fov(m.pos.0 as usize, m.pos.1 as usize)
+                    && (m.pos - ppos).len2() <= r * r
+            })
+            .map(|m| m.pos)
+            .collect();
+        targets.sort_by_key(|&p| (p - ppos).len2());
+        TargetScreen {
+            range,
+            cursor: Cell::new(targets.first().copied().unwrap_or(ppos)),
+            targets,
+            next: Cell::new(0),
+        }
+    }
+}
+
+impl Screen for TargetScreen {
+    fn render(&self, game: &mut Game, con: &mut Console) {
+        let offset = map_offset(game, con);
+        let level = game.levels.cur();
+        let ppos = level.monsters[0].pos;
+        let cursor = self.cursor.get();
+        for p in level.projectile_path(ppos, cursor, self.range) {
+            let p = p - offset;
+            con.back(p.0, p.1, DARK_AMBER);
+        }
+        let c = cursor - offset;
+        con.back(c.0, c.1, LIGHT_AMBER);
+        con.print_color(
+            0,
+            0,
+            "Fire where? (Tab for next target, Enter to fire)",
+            TextAlign::Left,
+            None,
+        );
+    }
+    fn handle(&self, game: &mut Game, key: Key) -> Action {
+        if let Some((d, _)) = DIR_KEYS.iter().find(|(_, keys)| keys.contains(&key.key)) {
+            self.cursor.set(self.cursor.get() + *d);
+            return Action::Keep;
+        }
+        match key {
+            Key { key: "Tab", .. } => {
+                if !self.targets.is_empty() {
+                    let len = self.targets.len();
+                    let next = if key.shift {
+                        (self.next.get() + len - 1) % len
+                    } else {
+                        (self.next.get() + 1) % len
+                    };
+                    self.next.set(next);
+                    self.cursor.set(self.targets[next]);
+                }
+                Action::Keep
+            }
+            Key { key: "Enter", .. }
+            | Key {
+                key: "NumpadEnter", ..
+            }
+            | Key { key: "KeyF", .. } => {
+                if self.cursor.get() == game.levels.cur().monsters[0].pos {
+                    return Action::Keep;
+                }
+                if game.fire(self.cursor.get()) {
+                    game.end_turn();
+                }
+                Action::Pop
+            }
+            _ => handle_default(game, key),
+        }
+    }
+    fn transparent(&self) -> bool {
+        true
+    }
+}
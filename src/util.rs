@@ -18,6 +18,17 @@ impl<T: Clone> Grid<T> {
         }
     }
 }
+impl<T> Grid<T> {
+    /// Builds a grid from its elements in row-major order
+    pub fn from_vec(elems: Vec<T>, width: usize) -> Grid<T> {
+        assert_eq!(elems.len() % width, 0);
+        Grid { width, elems }
+    }
+    /// The elements in row-major order
+    pub fn elems(&self) -> &[T] {
+        &self.elems
+    }
+}
 impl<T> Index<[usize; 2]> for Grid<T> {
     type Output = T;
     fn index(&self, [x, y]: [usize; 2]) -> &T {
@@ -80,6 +91,12 @@ mod tests {
         assert!(g[[1, 19]]);
     }
     #[test]
+    fn grid_from_vec() {
+        let g = Grid::from_vec(vec![1, 2, 3, 4, 5, 6], 3);
+        assert_eq!(g[[0, 1]], 4);
+        assert_eq!(g.elems(), &[1, 2, 3, 4, 5, 6]);
+    }
+    #[test]
     #[should_panic]
     fn grid_bounds() {
         let g = Grid::new(true, 2, 20);